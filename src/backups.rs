@@ -0,0 +1,169 @@
+use crate::error::Error;
+use crate::file_io::write_atomically;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+/// The number of backups that are kept for every file, older ones are deleted
+const MAX_BACKUPS: usize = 10;
+const BACKUP_DIRECTORY: &str = "backups";
+
+/// Copies the file into the backup directory next to it before it gets overwritten. The name
+/// of a backup is the timestamp followed by the name of the file, so backups sort by age.
+pub fn create_backup(file_path: &str) -> Result<(), Error> {
+    let path = Path::new(file_path);
+
+    let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Ok(());
+    };
+
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let backup_directory = get_backup_directory(path.parent().unwrap_or(Path::new(".")));
+    fs::create_dir_all(&backup_directory)
+        .map_err(|e| Error::io(&backup_directory.to_string_lossy(), e))?;
+
+    let timestamp = OffsetDateTime::now_utc()
+        .format(format_description!(
+            "[year][month][day]-[hour][minute][second]-[subsecond digits:6]"
+        ))
+        .expect("Failed to format the backup timestamp");
+
+    let backup_path = backup_directory.join(format!("{timestamp}_{file_name}"));
+    fs::copy(path, &backup_path).map_err(|e| Error::io(&backup_path.to_string_lossy(), e))?;
+
+    remove_old_backups(&backup_directory, file_name)
+}
+
+/// The names of all backups in the data directory, oldest first
+pub fn list_backups(data_directory: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(get_backup_directory(Path::new(data_directory))) else {
+        return vec![];
+    };
+
+    let mut backup_names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| get_original_file_name(name).is_some())
+        .collect();
+
+    backup_names.sort();
+    backup_names
+}
+
+/// Replaces the file the backup was created from with the backup. The current file is backed
+/// up first, so restoring can be undone. Returns the path of the restored file.
+pub fn restore_backup(data_directory: &str, backup_name: &str) -> Result<String, Error> {
+    let original_file_name = get_original_file_name(backup_name)
+        .filter(|_| {
+            list_backups(data_directory)
+                .iter()
+                .any(|n| n == backup_name)
+        })
+        .ok_or_else(|| {
+            Error::NotFound(format!(
+                "No backup with the name {backup_name} has been found"
+            ))
+        })?;
+
+    let backup_path = get_backup_directory(Path::new(data_directory)).join(backup_name);
+    let contents = fs::read_to_string(&backup_path)
+        .map_err(|e| Error::io(&backup_path.to_string_lossy(), e))?;
+    let file_path = format!("{data_directory}/{original_file_name}");
+
+    create_backup(&file_path)?;
+    write_atomically(&file_path, &contents)?;
+
+    Ok(file_path)
+}
+
+fn get_backup_directory(directory: &Path) -> PathBuf {
+    directory.join(BACKUP_DIRECTORY)
+}
+
+fn get_original_file_name(backup_name: &str) -> Option<&str> {
+    backup_name
+        .split_once('_')
+        .map(|(_, file_name)| file_name)
+        .filter(|file_name| !file_name.is_empty())
+}
+
+fn remove_old_backups(backup_directory: &Path, file_name: &str) -> Result<(), Error> {
+    let Some(data_directory) = backup_directory.parent().and_then(|d| d.to_str()) else {
+        return Ok(());
+    };
+
+    let backups_of_file: Vec<String> = list_backups(data_directory)
+        .into_iter()
+        .filter(|name| get_original_file_name(name) == Some(file_name))
+        .collect();
+
+    let number_of_old_backups = backups_of_file.len().saturating_sub(MAX_BACKUPS);
+
+    for backup_name in &backups_of_file[..number_of_old_backups] {
+        let backup_path = backup_directory.join(backup_name);
+        fs::remove_file(&backup_path).map_err(|e| Error::io(&backup_path.to_string_lossy(), e))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_directory(name: &str) -> String {
+        let directory =
+            std::env::temp_dir().join(format!("feedback-matching-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_backups_are_rotated_and_restored() {
+        let data_directory = test_directory("backups");
+        let file_path = format!("{data_directory}/matches.json");
+        let backup_directory = get_backup_directory(Path::new(&data_directory));
+        fs::create_dir_all(&backup_directory).unwrap();
+
+        for i in 0..MAX_BACKUPS {
+            let old_backup =
+                backup_directory.join(format!("2000010{i}-000000-000000_matches.json"));
+            fs::write(old_backup, format!("[{i}]")).unwrap();
+        }
+        fs::write(
+            backup_directory.join("20000101-000000-000000_participants.json"),
+            "{}",
+        )
+        .unwrap();
+        fs::write(&file_path, "[\"current\"]").unwrap();
+
+        create_backup(&file_path).unwrap();
+
+        let backups = list_backups(&data_directory);
+        assert_eq!(backups.len(), MAX_BACKUPS + 1);
+        assert!(!backups.contains(&"20000100-000000-000000_matches.json".to_string()));
+        assert!(backups.contains(&"20000101-000000-000000_participants.json".to_string()));
+
+        let restored_file_path =
+            restore_backup(&data_directory, "20000105-000000-000000_matches.json");
+
+        assert_eq!(restored_file_path.unwrap(), file_path);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "[5]");
+        assert_eq!(
+            fs::read_to_string(backup_directory.join(backups.last().unwrap())).unwrap(),
+            "[\"current\"]"
+        );
+        assert!(matches!(
+            restore_backup(&data_directory, "missing"),
+            Err(Error::NotFound(_))
+        ));
+
+        fs::remove_dir_all(&data_directory).unwrap();
+    }
+}
@@ -0,0 +1,330 @@
+use super::{DeliveryChannel, DeliveryLog, DeliveryRecord, DeliveryStatus};
+use crate::error::Error;
+use crate::matching::messages::Message;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// How the connection to the SMTP relay is secured
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmtpSecurity {
+    /// Plain text, only meant for local relays and test servers
+    None,
+    /// Upgrade the plain connection with STARTTLS, which is required
+    #[default]
+    StartTls,
+    /// TLS from the start of the connection
+    Tls,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// The default port of the security if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// The environment variable with the password, so it isn't stored in the data directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
+    /// The sender of the emails, e.g. "Feedback <feedback@example.com>"
+    pub from: String,
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+fn default_timeout_seconds() -> u64 {
+    10
+}
+
+/// Sends the message of every giver to the email address of the giver and records the outcome
+/// in the log, which is saved after every giver. Givers without email address count as failed.
+/// Givers who have already been sent their message are skipped unless `force` is set. With
+/// `dry_run` the emails are only printed. Returns the errors of the failed emails.
+pub fn send_emails(
+    messages: &[Message],
+    emails: &HashMap<u32, String>,
+    config: &SmtpConfig,
+    delivery_log: &mut DeliveryLog,
+    mut save_log: impl FnMut(&DeliveryLog) -> Result<(), Error>,
+    dry_run: bool,
+    force: bool,
+) -> Result<Vec<String>, Error> {
+    let transport = create_transport(config)?;
+    let from: Mailbox = config
+        .from
+        .parse()
+        .map_err(|e| Error::Delivery(vec![format!("Invalid sender {}: {e}", config.from)]))?;
+    let mut errors = vec![];
+
+    for message in messages {
+        let giver = &message.giver;
+
+        if !force && delivery_log.is_delivered(giver.id, DeliveryChannel::Email) {
+            println!(
+                "Already sent to {}, use --force to send it again",
+                giver.full_name()
+            );
+            continue;
+        }
+
+        let email = match emails.get(&giver.id) {
+            Some(email) => create_email(message, &from, email),
+            None => Err("No email address".to_string()),
+        };
+        // Emails that can't be created aren't attempted
+        let attempts = u32::from(email.is_ok());
+
+        let result = email.and_then(|email| {
+            if dry_run {
+                println!("\n{}", String::from_utf8_lossy(&email.formatted()));
+                return Ok(());
+            }
+
+            transport
+                .send(&email)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        });
+
+        if dry_run {
+            if let Err(error) = &result {
+                println!("\n{}: {error}", giver.full_name());
+            }
+            continue;
+        }
+
+        match &result {
+            Ok(()) => println!("Sent to {}", giver.full_name()),
+            Err(error) => errors.push(format!("{}: {error}", giver.full_name())),
+        }
+
+        delivery_log.deliveries.push(DeliveryRecord {
+            giver_id: giver.id,
+            channel: DeliveryChannel::Email,
+            status: match result {
+                Ok(()) => DeliveryStatus::Delivered,
+                Err(_) => DeliveryStatus::Failed,
+            },
+            attempts,
+            error: result.err(),
+            time: OffsetDateTime::now_utc(),
+        });
+
+        // Saved right away, so an interrupted run doesn't send the emails twice
+        save_log(delivery_log)?;
+    }
+
+    Ok(errors)
+}
+
+fn create_transport(config: &SmtpConfig) -> Result<SmtpTransport, Error> {
+    let builder = match config.security {
+        SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(&config.host)),
+        SmtpSecurity::StartTls => SmtpTransport::starttls_relay(&config.host),
+        SmtpSecurity::Tls => SmtpTransport::relay(&config.host),
+    }
+    .map_err(|e| Error::Delivery(vec![format!("Invalid SMTP relay {}: {e}", config.host)]))?;
+
+    let mut builder = builder.timeout(Some(Duration::from_secs(config.timeout_seconds)));
+
+    if let Some(port) = config.port {
+        builder = builder.port(port);
+    }
+
+    if let Some(username) = &config.username {
+        let password = match &config.password_env {
+            Some(variable) => env::var(variable).map_err(|_| {
+                Error::Delivery(vec![format!(
+                    "The environment variable {variable} with the SMTP password isn't set"
+                )])
+            })?,
+            None => String::new(),
+        };
+
+        builder = builder.credentials(Credentials::new(username.clone(), password));
+    }
+
+    Ok(builder.build())
+}
+
+fn create_email(message: &Message, from: &Mailbox, email: &str) -> Result<lettre::Message, String> {
+    let giver = &message.giver;
+    let to = Mailbox::new(
+        Some(giver.full_name()),
+        email
+            .parse()
+            .map_err(|e| format!("Invalid email address {email}: {e}"))?,
+    );
+
+    lettre::Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(giver.get_locale().message_subject())
+        .header(ContentType::TEXT_PLAIN)
+        .body(message.text.trim_end().to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::match_participant;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// A minimal SMTP server that accepts the number of emails and returns their recipients
+    fn serve(number_of_emails: usize) -> (u16, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = thread::spawn(move || {
+            (0..number_of_emails)
+                .map(|_| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut recipient = String::new();
+                    write!(stream, "220 localhost\r\n").unwrap();
+
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap() == 0 {
+                            break;
+                        }
+
+                        let reply = match &line[..4] {
+                            "EHLO" => "250 localhost",
+                            "RCPT" => {
+                                recipient = line.trim().to_string();
+                                "250 OK"
+                            }
+                            "DATA" => {
+                                write!(stream, "354 Go ahead\r\n").unwrap();
+                                while line != ".\r\n" {
+                                    line.clear();
+                                    reader.read_line(&mut line).unwrap();
+                                }
+                                "250 Queued"
+                            }
+                            "QUIT" => {
+                                write!(stream, "221 Bye\r\n").unwrap();
+                                break;
+                            }
+                            _ => "250 OK",
+                        };
+                        write!(stream, "{reply}\r\n").unwrap();
+                    }
+
+                    recipient
+                })
+                .collect()
+        });
+
+        (port, handle)
+    }
+
+    #[test]
+    fn test_emails_are_only_sent_again_if_forced() {
+        let messages = vec![Message {
+            giver: match_participant(1, 1),
+            receiver: match_participant(2, 1),
+            text: "Hi First1\n".to_string(),
+        }];
+        let (port, server) = serve(2);
+        let config = SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: None,
+            password_env: None,
+            from: "Feedback <feedback@example.com>".to_string(),
+            timeout_seconds: 5,
+        };
+        let emails = HashMap::from([(1, "first1@example.com".to_string())]);
+        let mut delivery_log = DeliveryLog::new(1);
+
+        for force in [false, false, true] {
+            let errors = send_emails(
+                &messages,
+                &emails,
+                &config,
+                &mut delivery_log,
+                |_| Ok(()),
+                false,
+                force,
+            )
+            .unwrap();
+            assert!(errors.is_empty());
+        }
+
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "RCPT TO:<first1@example.com>",
+                "RCPT TO:<first1@example.com>"
+            ]
+        );
+        assert_eq!(delivery_log.deliveries.len(), 2);
+        assert!(delivery_log.is_delivered(1, DeliveryChannel::Email));
+    }
+
+    #[test]
+    fn test_givers_without_email_address_fail() {
+        let messages = vec![
+            Message {
+                giver: match_participant(1, 1),
+                receiver: match_participant(2, 1),
+                text: "Hi First1\n".to_string(),
+            },
+            Message {
+                giver: match_participant(2, 1),
+                receiver: match_participant(1, 1),
+                text: "Hi First2\n".to_string(),
+            },
+        ];
+        let (port, server) = serve(1);
+        let config = SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: None,
+            password_env: None,
+            from: "Feedback <feedback@example.com>".to_string(),
+            timeout_seconds: 5,
+        };
+        let mut delivery_log = DeliveryLog::new(1);
+        let mut saved_lengths = vec![];
+
+        let errors = send_emails(
+            &messages,
+            &HashMap::from([(1, "first1@example.com".to_string())]),
+            &config,
+            &mut delivery_log,
+            |log| {
+                saved_lengths.push(log.deliveries.len());
+                Ok(())
+            },
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(server.join().unwrap(), vec!["RCPT TO:<first1@example.com>"]);
+        assert_eq!(errors, vec!["First2 Last2: No email address"]);
+        assert_eq!(delivery_log.deliveries[1].status, DeliveryStatus::Failed);
+        assert_eq!(delivery_log.deliveries[1].attempts, 0);
+        // The log is saved after every giver
+        assert_eq!(saved_lengths, vec![1, 2]);
+    }
+}
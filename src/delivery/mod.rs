@@ -0,0 +1,74 @@
+pub mod email;
+pub mod webhook;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use email::SmtpConfig;
+use webhook::WebhookConfig;
+
+/// How the messages are delivered, read from `delivery.json`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DeliveryConfig {
+    pub webhook: WebhookConfig,
+    /// The relay emails are sent through, sending emails fails without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<SmtpConfig>,
+}
+
+#[derive(clap::ValueEnum, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeliveryChannel {
+    /// The chat webhook of the giver
+    #[default]
+    Webhook,
+    /// An email to the giver through the SMTP relay
+    Email,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Delivered,
+    Failed,
+}
+
+/// The outcome of delivering the message of one giver, including all retries
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeliveryRecord {
+    pub giver_id: u32,
+    pub channel: DeliveryChannel,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// The error of the last attempt if the delivery failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+}
+
+/// Every delivery of the messages of a round, oldest first. It's stored next to the matches, so
+/// a resend only targets the givers whose delivery failed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeliveryLog {
+    pub round_id: i32,
+    pub deliveries: Vec<DeliveryRecord>,
+}
+
+impl DeliveryLog {
+    pub fn new(round_id: i32) -> DeliveryLog {
+        DeliveryLog {
+            round_id,
+            deliveries: vec![],
+        }
+    }
+
+    pub fn file_name(round_id: i32) -> String {
+        format!("delivery_log_round_{round_id}.json")
+    }
+
+    pub fn is_delivered(&self, giver_id: u32, channel: DeliveryChannel) -> bool {
+        self.deliveries.iter().any(|d| {
+            d.giver_id == giver_id && d.channel == channel && d.status == DeliveryStatus::Delivered
+        })
+    }
+}
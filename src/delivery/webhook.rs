@@ -0,0 +1,323 @@
+use super::{DeliveryChannel, DeliveryLog, DeliveryRecord, DeliveryStatus};
+use crate::error::Error;
+use crate::matching::messages::Message;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// The JSON payload the chat expects from incoming webhooks
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadFormat {
+    #[default]
+    Slack,
+    Mattermost,
+    Teams,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub format: PayloadFormat,
+    /// How often a failed request is retried
+    pub retries: u32,
+    /// The delay before the first retry, it doubles with every further retry
+    pub retry_delay_milliseconds: u64,
+    pub timeout_seconds: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            format: PayloadFormat::Slack,
+            retries: 3,
+            retry_delay_milliseconds: 1000,
+            timeout_seconds: 10,
+        }
+    }
+}
+
+pub fn create_payload(format: PayloadFormat, message: &Message) -> Value {
+    let text = message.text.trim_end();
+
+    match format {
+        // Mattermost accepts the payload of Slack
+        PayloadFormat::Slack | PayloadFormat::Mattermost => json!({ "text": text }),
+        // Teams only breaks lines at paragraphs
+        PayloadFormat::Teams => json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": message.giver.get_locale().message_subject(),
+            "text": text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .collect::<Vec<&str>>()
+                .join("\n\n"),
+        }),
+    }
+}
+
+/// Posts the message of every giver to the giver's webhook and records the outcome in the log,
+/// which is saved after every giver. Givers without webhook URL count as failed. Givers whose
+/// message has already been delivered are skipped unless `force` is set. With `dry_run` the
+/// payloads are only printed. Returns the errors of the failed deliveries.
+pub fn deliver_messages(
+    messages: &[Message],
+    webhook_urls: &HashMap<u32, String>,
+    config: &WebhookConfig,
+    delivery_log: &mut DeliveryLog,
+    mut save_log: impl FnMut(&DeliveryLog) -> Result<(), Error>,
+    dry_run: bool,
+    force: bool,
+) -> Result<Vec<String>, Error> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(config.timeout_seconds))
+        .build();
+    let mut errors = vec![];
+
+    for message in messages {
+        let giver = &message.giver;
+
+        if !force && delivery_log.is_delivered(giver.id, DeliveryChannel::Webhook) {
+            println!(
+                "Already delivered to {}, use --force to deliver it again",
+                giver.full_name()
+            );
+            continue;
+        }
+
+        let payload = create_payload(config.format, message);
+
+        if dry_run {
+            match webhook_urls.get(&giver.id) {
+                Some(url) => println!(
+                    "\nPOST {url}\n{}",
+                    serde_json::to_string_pretty(&payload)
+                        .expect("Failed to serialize the payload")
+                ),
+                None => println!("\n{} has no webhook URL", giver.full_name()),
+            }
+            continue;
+        }
+
+        let (attempts, result) = match webhook_urls.get(&giver.id) {
+            Some(url) => post_with_retries(&agent, url, &payload, config),
+            None => (0, Err("No webhook URL".to_string())),
+        };
+
+        match &result {
+            Ok(()) => println!("Delivered to {}", giver.full_name()),
+            Err(error) => errors.push(format!("{}: {error}", giver.full_name())),
+        }
+
+        delivery_log.deliveries.push(DeliveryRecord {
+            giver_id: giver.id,
+            channel: DeliveryChannel::Webhook,
+            status: match result {
+                Ok(()) => DeliveryStatus::Delivered,
+                Err(_) => DeliveryStatus::Failed,
+            },
+            attempts,
+            error: result.err(),
+            time: OffsetDateTime::now_utc(),
+        });
+
+        // Saved right away, so an interrupted run doesn't deliver the messages twice
+        save_log(delivery_log)?;
+    }
+
+    Ok(errors)
+}
+
+/// Retries transport errors, rate limits and server errors, other responses are final. Returns
+/// the number of attempts and the outcome of the last one.
+fn post_with_retries(
+    agent: &ureq::Agent,
+    url: &str,
+    payload: &Value,
+    config: &WebhookConfig,
+) -> (u32, Result<(), String>) {
+    let mut delay = Duration::from_millis(config.retry_delay_milliseconds);
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+
+        let (result, retryable) = match agent.post(url).send_json(payload) {
+            Ok(_) => (Ok(()), false),
+            Err(ureq::Error::Status(status, response)) => (
+                Err(format!("{status} {}", response.status_text())),
+                status == 429 || status >= 500,
+            ),
+            Err(error) => (Err(error.to_string()), true),
+        };
+
+        if !retryable || attempts > config.retries {
+            return (attempts, result);
+        }
+
+        thread::sleep(delay);
+        delay *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::match_participant;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Answers the requests with the statuses in order and returns the received bodies
+    fn serve(statuses: Vec<u16>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            statuses
+                .into_iter()
+                .map(|status| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut content_length = 0;
+
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+
+                        if line == "\r\n" {
+                            break;
+                        }
+                    }
+
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    write!(
+                        reader.get_mut(),
+                        "HTTP/1.1 {status} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                    .unwrap();
+
+                    String::from_utf8(body).unwrap()
+                })
+                .collect()
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    fn test_failed_deliveries_are_retried_and_resent() {
+        let messages = vec![
+            Message {
+                giver: match_participant(1, 1),
+                receiver: match_participant(2, 1),
+                text: "Hi First1\n".to_string(),
+            },
+            Message {
+                giver: match_participant(2, 1),
+                receiver: match_participant(1, 1),
+                text: "Hi First2\n".to_string(),
+            },
+        ];
+        let config = WebhookConfig {
+            retries: 1,
+            retry_delay_milliseconds: 1,
+            ..WebhookConfig::default()
+        };
+        let mut delivery_log = DeliveryLog::new(1);
+
+        // The first giver succeeds on the retry, the second one fails for good
+        let (url, server) = serve(vec![500, 200, 404]);
+        let webhook_urls = HashMap::from([(1, url.clone()), (2, url)]);
+        let errors = deliver_messages(
+            &messages,
+            &webhook_urls,
+            &config,
+            &mut delivery_log,
+            |_| Ok(()),
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                r#"{"text":"Hi First1"}"#,
+                r#"{"text":"Hi First1"}"#,
+                r#"{"text":"Hi First2"}"#
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(delivery_log.deliveries[0].attempts, 2);
+        assert_eq!(delivery_log.deliveries[1].status, DeliveryStatus::Failed);
+
+        // The resend only targets the failed giver
+        let (url, server) = serve(vec![200]);
+        let webhook_urls = HashMap::from([(1, url.clone()), (2, url)]);
+        let errors = deliver_messages(
+            &messages,
+            &webhook_urls,
+            &config,
+            &mut delivery_log,
+            |_| Ok(()),
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(server.join().unwrap(), vec![r#"{"text":"Hi First2"}"#]);
+        assert!(errors.is_empty());
+        assert!(delivery_log.is_delivered(2, DeliveryChannel::Webhook));
+    }
+
+    #[test]
+    fn test_givers_without_webhook_url_fail() {
+        let messages = vec![
+            Message {
+                giver: match_participant(1, 1),
+                receiver: match_participant(2, 1),
+                text: "Hi First1\n".to_string(),
+            },
+            Message {
+                giver: match_participant(2, 1),
+                receiver: match_participant(1, 1),
+                text: "Hi First2\n".to_string(),
+            },
+        ];
+        let mut delivery_log = DeliveryLog::new(1);
+        let mut saved_lengths = vec![];
+
+        let (url, server) = serve(vec![200]);
+        let errors = deliver_messages(
+            &messages,
+            &HashMap::from([(1, url)]),
+            &WebhookConfig::default(),
+            &mut delivery_log,
+            |log| {
+                saved_lengths.push(log.deliveries.len());
+                Ok(())
+            },
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(server.join().unwrap().len(), 1);
+        assert_eq!(errors, vec!["First2 Last2: No webhook URL"]);
+        assert_eq!(delivery_log.deliveries[1].status, DeliveryStatus::Failed);
+        assert_eq!(delivery_log.deliveries[1].attempts, 0);
+        // The log is saved after every giver
+        assert_eq!(saved_lengths, vec![1, 2]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::process::ExitCode;
+
+/// Everything that can make a command fail
+#[derive(Debug)]
+pub enum Error {
+    /// A file couldn't be read or written
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    /// A file isn't valid JSON or doesn't fit the data structures
+    Json {
+        path: String,
+        source: serde_json::Error,
+    },
+    /// A CSV file couldn't be read or written
+    Csv {
+        path: String,
+        source: csv::Error,
+    },
+    /// The data of a file doesn't match its JSON schema
+    Validation {
+        path: String,
+        errors: Vec<String>,
+    },
+    Database(rusqlite::Error),
+    /// A message template uses unknown placeholders or unbalanced braces
+    Template {
+        path: String,
+        errors: Vec<String>,
+    },
+    /// The constraints of the participants file can't be fulfilled
+    Constraints(Vec<String>),
+    /// The requested data doesn't exist
+    NotFound(String),
+    /// Messages couldn't be delivered, the errors of the recipients
+    Delivery(Vec<String>),
+}
+
+impl Error {
+    pub fn io(path: &str, source: io::Error) -> Error {
+        Error::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    pub fn json(path: &str, source: serde_json::Error) -> Error {
+        Error::Json {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    pub fn csv(path: &str, source: csv::Error) -> Error {
+        Error::Csv {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    /// Distinct exit codes, so scripts can tell invalid data from missing data
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Error::Io { .. } | Error::Json { .. } | Error::Csv { .. } | Error::Database(_) => {
+                ExitCode::from(1)
+            }
+            Error::Validation { .. } | Error::Template { .. } => ExitCode::from(2),
+            Error::Constraints(_) => ExitCode::from(3),
+            Error::NotFound(_) => ExitCode::from(4),
+            Error::Delivery(_) => ExitCode::from(5),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "Failed to access {path}: {source}"),
+            Error::Json { path, source } => write!(f, "Failed to parse {path}: {source}"),
+            Error::Csv { path, source } => write!(f, "Failed to process {path}: {source}"),
+            Error::Validation { path, errors } => {
+                write!(f, "{path} doesn't match its schema")?;
+                write_numbered(f, "Validation error", errors)
+            }
+            Error::Database(source) => write!(f, "Database error: {source}"),
+            Error::Template { path, errors } => {
+                write!(f, "{path} isn't a valid message template")?;
+                write_numbered(f, "Template error", errors)
+            }
+            Error::Constraints(errors) => {
+                write!(f, "The constraints can't be fulfilled")?;
+                write_numbered(f, "Constraint error", errors)
+            }
+            Error::NotFound(message) => write!(f, "{message}"),
+            Error::Delivery(errors) => {
+                write!(f, "Some messages couldn't be delivered")?;
+                write_numbered(f, "Delivery error", errors)
+            }
+        }
+    }
+}
+
+fn write_numbered(f: &mut Formatter<'_>, label: &str, errors: &[String]) -> fmt::Result {
+    for (i, error) in errors.iter().enumerate() {
+        write!(f, "\n{}.{label}: {error}", i + 1)?;
+    }
+
+    Ok(())
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Json { source, .. } => Some(source),
+            Error::Csv { source, .. } => Some(source),
+            Error::Database(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(source: rusqlite::Error) -> Self {
+        Error::Database(source)
+    }
+}
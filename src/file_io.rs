@@ -1,60 +1,136 @@
+use crate::backups::create_backup;
+use crate::delivery::{DeliveryConfig, DeliveryLog};
+use crate::error::Error;
+use crate::matching::scoring::ScoringConfig;
+use crate::storage::StorageConfig;
+use crate::structs::calendar::HolidayCalendar;
 use crate::structs::matching_round::MatchingRound;
 use crate::structs::participants_file::ParticipantsFile;
 
 use jsonschema::JSONSchema;
 use serde::de::DeserializeOwned;
-use serde_json::{from_reader, Value};
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
 
 const MATCHES_SCHEMA: &[u8] = include_bytes!("../data/schema/matches_schema.json");
 const PARTICIPANTS_SCHEMA: &[u8] = include_bytes!("../data/schema/participants_schema.json");
+const SCORING_SCHEMA: &[u8] = include_bytes!("../data/schema/scoring_schema.json");
+const STORAGE_SCHEMA: &[u8] = include_bytes!("../data/schema/storage_schema.json");
+const HOLIDAYS_SCHEMA: &[u8] = include_bytes!("../data/schema/holidays_schema.json");
+const DELIVERY_SCHEMA: &[u8] = include_bytes!("../data/schema/delivery_schema.json");
+const DELIVERY_LOG_SCHEMA: &[u8] = include_bytes!("../data/schema/delivery_log_schema.json");
+
+/// How to handle data that doesn't match its JSON schema when it's read. Data is always
+/// validated before it's written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// Fail on any validation error
+    #[default]
+    Strict,
+    /// Print the validation errors as warnings and try to read the data anyway
+    Lenient,
+}
 
-pub fn read_participants(file_path: &str) -> ParticipantsFile {
-    read::<ParticipantsFile>(file_path, PARTICIPANTS_SCHEMA)
+pub fn read_participants(file_path: &str, mode: ValidationMode) -> Result<ParticipantsFile, Error> {
+    read::<ParticipantsFile>(file_path, PARTICIPANTS_SCHEMA, mode)
 }
 
-pub fn read_matching_rounds(file_path: &str) -> Vec<MatchingRound> {
-    read::<Vec<MatchingRound>>(file_path, MATCHES_SCHEMA)
+pub fn read_matching_rounds(
+    file_path: &str,
+    mode: ValidationMode,
+) -> Result<Vec<MatchingRound>, Error> {
+    read::<Vec<MatchingRound>>(file_path, MATCHES_SCHEMA, mode)
 }
 
-fn read<T>(file_path: &str, schema: &[u8]) -> T
-where
-    T: DeserializeOwned,
-{
-    let compiled_schema = load_schema(schema);
+/// Reads the scoring config, falling back to the default scoring if the file doesn't exist
+pub fn read_scoring_config(file_path: &str, mode: ValidationMode) -> Result<ScoringConfig, Error> {
+    if !Path::new(file_path).exists() {
+        return Ok(ScoringConfig::default());
+    }
 
-    let data_string = read_string(file_path);
-    let json_data: Value =
-        serde_json::from_str(data_string.as_str()).expect("Failed to parse JSON data");
+    read::<ScoringConfig>(file_path, SCORING_SCHEMA, mode)
+}
 
-    let validation_result = compiled_schema.validate(&json_data);
+/// Reads the storage config, falling back to the JSON files if the file doesn't exist
+pub fn read_storage_config(file_path: &str, mode: ValidationMode) -> Result<StorageConfig, Error> {
+    if !Path::new(file_path).exists() {
+        return Ok(StorageConfig::default());
+    }
 
-    if let Err(errors) = validation_result {
-        println!("\n### Read JSON Validation Errors ###\n");
+    read::<StorageConfig>(file_path, STORAGE_SCHEMA, mode)
+}
 
-        for (i, error) in errors.enumerate() {
-            println!(
-                "{}.Validation error: {}\nInstance path: {}\n",
-                i + 1,
-                error,
-                error.instance_path
-            );
-        }
+/// Reads the blocked days, falling back to an empty calendar if the file doesn't exist
+pub fn read_holiday_calendar(
+    file_path: &str,
+    mode: ValidationMode,
+) -> Result<HolidayCalendar, Error> {
+    if !Path::new(file_path).exists() {
+        return Ok(HolidayCalendar::default());
+    }
+
+    read::<HolidayCalendar>(file_path, HOLIDAYS_SCHEMA, mode)
+}
 
-        print!("###\n\n")
+/// Reads the delivery config, falling back to the default config if the file doesn't exist
+pub fn read_delivery_config(
+    file_path: &str,
+    mode: ValidationMode,
+) -> Result<DeliveryConfig, Error> {
+    if !Path::new(file_path).exists() {
+        return Ok(DeliveryConfig::default());
     }
 
-    from_reader(data_string.as_bytes()).expect("Failed to parse JSON")
+    read::<DeliveryConfig>(file_path, DELIVERY_SCHEMA, mode)
 }
 
-fn read_string(file_path: &str) -> String {
-    let mut file = File::open(file_path).expect("Failed to open JSON file");
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .expect("Failed to read JSON file");
+/// Reads the delivery log of the round, which is empty if nothing has been delivered yet
+pub fn read_delivery_log(
+    file_path: &str,
+    round_id: i32,
+    mode: ValidationMode,
+) -> Result<DeliveryLog, Error> {
+    if !Path::new(file_path).exists() {
+        return Ok(DeliveryLog::new(round_id));
+    }
 
-    contents
+    read::<DeliveryLog>(file_path, DELIVERY_LOG_SCHEMA, mode)
+}
+
+fn read<T>(file_path: &str, schema: &[u8], mode: ValidationMode) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let data_string = fs::read_to_string(file_path).map_err(|e| Error::io(file_path, e))?;
+    let json_data: Value =
+        serde_json::from_str(&data_string).map_err(|e| Error::json(file_path, e))?;
+
+    if let Err(error) = validate(file_path, &json_data, schema) {
+        match mode {
+            ValidationMode::Strict => return Err(error),
+            ValidationMode::Lenient => {
+                eprintln!("\n### Read JSON Validation Warnings ###\n{error}\n")
+            }
+        }
+    }
+
+    serde_json::from_value(json_data).map_err(|e| Error::json(file_path, e))
+}
+
+fn validate(file_path: &str, json_data: &Value, schema: &[u8]) -> Result<(), Error> {
+    let compiled_schema = load_schema(schema);
+
+    compiled_schema
+        .validate(json_data)
+        .map_err(|errors| Error::Validation {
+            path: file_path.to_string(),
+            errors: errors
+                .map(|error| format!("{error}, instance path: {}", error.instance_path))
+                .collect(),
+        })
 }
 
 fn load_schema(schema: &[u8]) -> JSONSchema {
@@ -62,56 +138,73 @@ fn load_schema(schema: &[u8]) -> JSONSchema {
     JSONSchema::compile(&schema).expect("Failed to compile Schema")
 }
 
-pub fn save_matching_round(file_path: &str, round: MatchingRound) {
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(file_path)
-        .expect("Failed to open matches file");
-
-    let mut existing_rounds: Vec<MatchingRound> = from_reader(&file).unwrap_or_else(|_| Vec::new());
+/// Appends the round to the matching rounds of the file, which is created if it doesn't exist
+pub fn save_matching_round(
+    file_path: &str,
+    round: MatchingRound,
+    mode: ValidationMode,
+) -> Result<(), Error> {
+    let mut existing_rounds = match Path::new(file_path).exists() {
+        true => read_matching_rounds(file_path, mode)?,
+        false => Vec::new(),
+    };
 
     existing_rounds.push(round);
 
     update_all_existing_rounds(file_path, &existing_rounds)
 }
 
-pub fn update_all_existing_rounds(file_path: &str, existing_rounds: &Vec<MatchingRound>) {
-    let schema = load_schema(MATCHES_SCHEMA);
+pub fn update_all_existing_rounds(
+    file_path: &str,
+    existing_rounds: &[MatchingRound],
+) -> Result<(), Error> {
+    write(file_path, existing_rounds, MATCHES_SCHEMA)
+}
+
+pub fn save_participants(
+    file_path: &str,
+    participants_file: &ParticipantsFile,
+) -> Result<(), Error> {
+    write(file_path, participants_file, PARTICIPANTS_SCHEMA)
+}
 
-    let value = serde_json::to_value(&existing_rounds).expect("Failed to serialize MatchingRound");
-    let validation_result = schema.validate(&value);
+/// The log is saved after every delivery, so it isn't backed up, the backups would push the
+/// ones of the participants and matches out of the rotation
+pub fn save_delivery_log(file_path: &str, delivery_log: &DeliveryLog) -> Result<(), Error> {
+    let contents = to_validated_json(file_path, delivery_log, DELIVERY_LOG_SCHEMA)?;
 
-    if let Err(errors) = validation_result {
-        println!("\n### Write JSON Validation Errors ###\n");
+    write_atomically(file_path, &contents)
+}
 
-        for (i, error) in errors.enumerate() {
-            println!(
-                "{}.Validation error: {}\nInstance path: {}\n",
-                i + 1,
-                error,
-                error.instance_path
-            );
-        }
+fn write<T>(file_path: &str, data: &T, schema: &[u8]) -> Result<(), Error>
+where
+    T: Serialize + ?Sized,
+{
+    let contents = to_validated_json(file_path, data, schema)?;
 
-        print!("###\n\n");
+    create_backup(file_path)?;
+    write_atomically(file_path, &contents)
+}
 
-        return;
-    }
+fn to_validated_json<T>(file_path: &str, data: &T, schema: &[u8]) -> Result<String, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let value = serde_json::to_value(data).map_err(|e| Error::json(file_path, e))?;
+    validate(file_path, &value, schema)?;
 
-    let mut file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(file_path)
-        .expect("Failed to open matches file");
+    serde_json::to_string_pretty(data).map_err(|e| Error::json(file_path, e))
+}
 
-    file.seek(SeekFrom::Start(0))
-        .expect("Failed to seek to the beginning of the file");
+/// Writes the contents to a temporary file next to the file and renames it afterwards, so the
+/// file is never left half written
+pub fn write_atomically(file_path: &str, contents: &str) -> Result<(), Error> {
+    let temp_file_path = format!("{file_path}.tmp");
 
-    file.set_len(0).expect("Failed to truncate the file");
+    let mut file = File::create(&temp_file_path).map_err(|e| Error::io(&temp_file_path, e))?;
+    file.write_all(contents.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| Error::io(&temp_file_path, e))?;
 
-    serde_json::to_writer_pretty(&mut file, &existing_rounds)
-        .expect("Failed to write matching rounds to file");
+    fs::rename(&temp_file_path, file_path).map_err(|e| Error::io(file_path, e))
 }
@@ -1,32 +1,114 @@
+mod backups;
+mod delivery;
+mod error;
 mod file_io;
 mod matching;
 mod migrations;
+mod participants_csv;
+mod storage;
 mod structs;
+#[cfg(test)]
+mod test_fixtures;
 
+use backups::{list_backups, restore_backup};
 use clap::{Parser, Subcommand};
+use delivery::{email::send_emails, webhook::deliver_messages, DeliveryChannel, DeliveryLog};
+use error::Error;
 use file_io::{
-    read_matching_rounds, read_participants, save_matching_round, update_all_existing_rounds,
+    read_delivery_config, read_delivery_log, read_holiday_calendar, read_scoring_config,
+    save_delivery_log, ValidationMode,
 };
 use matching::{
-    complete_givers::get_complete_givers, matching::match_participants,
-    messages::print_messages_for_round,
+    calendar_export::{export_calendar, CalendarOptions},
+    complete_givers::get_complete_givers,
+    constraints::MatchingConstraints,
+    feedback_date::{get_feedback_dates, get_feedback_dates_for_intervall},
+    history_export::{export_history, export_matrices, MatrixFormat, MatrixValue},
+    html_report::write_html_report,
+    matcher::{count_reciprocal_matches, MatchingStrategy, ReciprocalMatches},
+    matching::{match_participants, MatchingOptions},
+    message_export::{MessageFormat, MessageOutput},
+    message_template::MessageTemplates,
+    messages::{generate_messages, print_messages_for_round},
+    objective::Objective,
+    round_summary::{
+        get_score_counts, get_score_range, get_settings_info, get_switch_info, ScoreRange,
+    },
+    scoring::ScoringConfig,
+    stats::{get_stats, StatsFormat},
+};
+use migrations::{
+    calculate_scores, update_matching_rounds_with_feedback_dates,
+    update_matching_rounds_with_group_ids,
 };
-use migrations::{calculate_scores, update_matching_rounds_with_group_ids};
-use rand::SeedableRng;
-use rand_chacha::ChaCha8Rng;
+use participants_csv::{import_csv, write_participants_csv};
+use std::collections::HashMap;
+use std::process::ExitCode;
+use storage::{json_storage::JsonStorage, DataSource, Storage};
+use structs::locale::Locale;
 use structs::matching_round::MatchingRound;
+use structs::participant::{Gender, Participant};
+use structs::participants_file::ParticipantsFile;
+use structs::r#match::Match;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime, Time};
 
 pub const NUMBER_OF_TRIES: i32 = 50;
+pub const DEFAULT_INTERVALL_WEEKS: i32 = 4;
 pub const MAX_SCORE: i64 = 1000000;
+pub const RECIPROCAL_PENALTY: i64 = MAX_SCORE;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     #[command(subcommand)]
     command: Commands,
-    /// The path to the directory where matches and participants are saved
+    /// The path to the directory where matches and participants are saved, or the database
+    /// file prefixed with `sqlite://`
     #[arg(short, long, default_value_t = {"./data/".to_string()})]
     data_path: String,
+    /// Fail if a file doesn't match its schema (the default)
+    #[arg(long, global = true, overrides_with = "lenient")]
+    strict: bool,
+    /// Print schema validation errors of read files as warnings and continue
+    #[arg(long, global = true, overrides_with = "strict")]
+    lenient: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct MessageOutputArgs {
+    /// How the messages are output
+    #[arg(long, value_enum, default_value_t = MessageFormat::Print)]
+    message_format: MessageFormat,
+    /// The directory the message files are written to, `messages/round_<id>` in the data
+    /// directory if omitted
+    #[arg(long)]
+    message_directory: Option<String>,
+    /// The From address of the email drafts
+    #[arg(long)]
+    sender: Option<String>,
+}
+
+impl MessageOutputArgs {
+    fn for_round(
+        &self,
+        matching_round: &MatchingRound,
+        participants_file: &ParticipantsFile,
+        data_source: &DataSource,
+    ) -> MessageOutput {
+        MessageOutput {
+            format: self.message_format,
+            directory: self.message_directory.clone().unwrap_or_else(|| {
+                format!(
+                    "{}/messages/round_{}",
+                    data_source.directory(),
+                    matching_round.id
+                )
+            }),
+            sender: self.sender.clone(),
+            emails: participants_file.get_emails(),
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -35,17 +117,41 @@ enum Commands {
     PastMatchMessages {
         /// The matching round id to print messages for
         matching_round_id: Option<i32>,
-        /// The number of weeks to separate the matches
-        #[arg(short, long, default_value_t = 4)]
-        intervall_weeks: i32,
+        /// The number of weeks to separate the matches, overrides the interval recorded with the
+        /// round (4 for rounds without one if omitted)
+        #[arg(short, long)]
+        intervall_weeks: Option<i32>,
+        #[command(flatten)]
+        message_output: MessageOutputArgs,
     },
     /// Print the messages for a past matching round
     PastRoundSummary {
         /// The matching round id to print messages for
         matching_round_id: Option<i32>,
+        /// Recalculate the scores with the current scoring config instead of the saved ones, as
+        /// on the date of the round
+        #[arg(short, long)]
+        rescore: bool,
     },
     /// Print the complete givers for every group
     CompleteGivers {},
+    /// Print per group and participant how many of the possible receivers have been matched, the
+    /// pairs that have never been matched and how often everyone was giver and receiver
+    Stats {
+        #[arg(short, long, value_enum, default_value_t)]
+        format: StatsFormat,
+    },
+    /// Write an HTML report with the rounds, the coverage of every group, the score trend, the
+    /// score distribution of every round and the complete givers
+    Report {
+        /// The HTML file, report.html in the data directory by default
+        #[arg(short, long)]
+        output: Option<String>,
+        /// The date of the report as YYYY-MM-DD, the days since the last match and the pair
+        /// scores are calculated for it, today if omitted
+        #[arg(long, value_parser = parse_date)]
+        as_of: Option<Date>,
+    },
     /// Create a new match
     CreateMatch {
         /// Save the output to matches.json
@@ -54,224 +160,919 @@ enum Commands {
         /// Print the messages for each match
         #[arg(short, long)]
         messages_generate: bool,
-        /// The number of weeks to separate the matches (only really relevant for the messages)
-        #[arg(short, long, default_value_t = 4)]
+        #[command(flatten)]
+        message_output: MessageOutputArgs,
+        /// The number of weeks to separate the matches, the feedback dates are based on it
+        #[arg(short, long, default_value_t = DEFAULT_INTERVALL_WEEKS)]
         intervall_weeks: i32,
         /// Match cross-teams
         #[arg(short, long)]
         cross_team_round: bool,
+        /// The strategy used to find the matches
+        #[arg(short, long, value_enum, default_value_t = MatchingStrategy::Greedy)]
+        strategy: MatchingStrategy,
+        /// The number of tries of the greedy strategy
+        #[arg(short, long, default_value_t = NUMBER_OF_TRIES)]
+        tries: i32,
+        /// The seed for the random number generator, a random one is generated if omitted
+        #[arg(long)]
+        seed: Option<u64>,
+        /// How to handle participants giving feedback to each other in the same round
+        #[arg(short, long, value_enum, default_value_t = ReciprocalMatches::Allow)]
+        reciprocal_matches: ReciprocalMatches,
+        /// Count a past match as recent for the reverse direction of the pair as well
+        #[arg(long)]
+        reverse_matches_as_recent: bool,
+        /// What makes one matching better than another
+        #[arg(short, long, value_enum, default_value_t = Objective::Mean)]
+        objective: Objective,
+        /// The lowest score every match should reach for the mean-with-floor objective
+        #[arg(short = 'f', long, default_value_t = 0)]
+        score_floor: i64,
+        /// The date of the round as YYYY-MM-DD, today if omitted. The scores count the days since
+        /// the past matches until then, so a round can be planned ahead of time.
+        #[arg(long, value_parser = parse_date)]
+        as_of: Option<Date>,
     },
     /// Deletes the last match
     DeleteMatch {},
     /// Execute data migrations
-    CalculateAndSaveScores {},
+    CalculateAndSaveScores {
+        /// The date as YYYY-MM-DD until which the days since the past matches are counted, today
+        /// if omitted
+        #[arg(long, value_parser = parse_date)]
+        as_of: Option<Date>,
+    },
     /// Execute data migrations
     AddGroupIdsToPastMatchParticipants {},
+    /// Execute data migrations: record the interval and feedback dates of past matching rounds
+    AddFeedbackDatesToPastMatchingRounds {
+        /// The number of weeks the past matching rounds were separated by
+        #[arg(short, long, default_value_t = DEFAULT_INTERVALL_WEEKS)]
+        intervall_weeks: i32,
+    },
+    /// List the backups of the JSON files or restore one of them
+    RestoreBackup {
+        /// The name of the backup to restore, the backups are listed if omitted
+        backup_name: Option<String>,
+    },
+    /// Import the participants and matches of a JSON data directory into the storage of the
+    /// data path, replacing its data
+    ImportJsonData {
+        /// The directory with the participants.json and matches.json to import
+        json_data_path: String,
+    },
+    /// Replace the participants with the roster of a CSV file with the columns id, first_name,
+    /// last_name, gender, group and excluded, and optionally email, locale and webhook_url.
+    /// People without id keep the id of the participant with the same email address or name.
+    Import {
+        csv_path: String,
+        /// Only print the added, removed and moved participants
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write the participants to a CSV file with one row per participant
+    Export { csv_path: String },
+    /// Write every match of the past rounds with round id, date, giver, receiver, groups and
+    /// score to a CSV file
+    ExportHistory {
+        csv_path: String,
+        /// Also write a giver×receiver matrix per group into the directory
+        #[arg(long)]
+        matrix_directory: Option<String>,
+        #[arg(long, value_enum, default_value_t)]
+        matrix_value: MatrixValue,
+        #[arg(long, value_enum, default_value_t)]
+        matrix_format: MatrixFormat,
+    },
+    /// Add, remove, exclude, include, move or list participants
+    Participant {
+        #[command(subcommand)]
+        command: ParticipantCommands,
+    },
+    /// Write calendar invites for the feedback conversations of a matching round
+    ExportCalendar {
+        /// The matching round id to write invites for
+        matching_round_id: Option<i32>,
+        /// The number of weeks to separate the matches, overrides the interval recorded with the
+        /// round (4 for rounds without one if omitted)
+        #[arg(short, long)]
+        intervall_weeks: Option<i32>,
+        /// The start time of the feedback conversations, e.g. 14:30
+        #[arg(short, long, default_value = "10:00", value_parser = parse_time)]
+        start_time: Time,
+        /// The length of the feedback conversations in minutes
+        #[arg(long, default_value_t = 30)]
+        duration_minutes: i64,
+        /// Write one file with all conversations instead of one file per match
+        #[arg(short, long)]
+        per_round: bool,
+        /// The directory the invites are written to, `messages/round_<id>` in the data directory
+        /// if omitted
+        #[arg(long)]
+        directory: Option<String>,
+    },
+    /// Post the messages of a matching round to the webhooks of the givers or email them.
+    /// Messages that have been delivered already are skipped, so running it again resends the
+    /// failed ones.
+    DeliverMessages {
+        /// The matching round id to deliver messages for
+        matching_round_id: Option<i32>,
+        /// The number of weeks to separate the matches, overrides the interval recorded with the
+        /// round (4 for rounds without one if omitted)
+        #[arg(short, long)]
+        intervall_weeks: Option<i32>,
+        /// How the messages are delivered
+        #[arg(short, long, value_enum, default_value_t = DeliveryChannel::Webhook)]
+        channel: DeliveryChannel,
+        /// Print the payloads or emails instead of sending them
+        #[arg(long)]
+        dry_run: bool,
+        /// Send the messages again to givers who already got them
+        #[arg(long)]
+        force: bool,
+    },
 }
 
-fn main() {
-    let Args { command, data_path } = Args::parse();
+#[derive(Subcommand, Debug)]
+enum ParticipantCommands {
+    /// List the participants of every group, with their ids
+    List {},
+    /// Add a participant with a new id
+    Add {
+        first_name: String,
+        last_name: String,
+        #[arg(value_enum)]
+        gender: Gender,
+        /// The group of the participant, it's created if it doesn't exist yet
+        #[arg(long)]
+        group: i32,
+        #[arg(long)]
+        email: Option<String>,
+        /// The locale of the participant, the one of the group if omitted
+        #[arg(long, value_enum)]
+        locale: Option<Locale>,
+        /// Add the participant to the excluded participants
+        #[arg(long)]
+        excluded: bool,
+    },
+    /// Remove a participant and the pair constraints with them, their past matches are kept
+    Remove { participant_id: u32 },
+    /// Exclude a participant from the next matching rounds
+    Exclude { participant_id: u32 },
+    /// Include an excluded participant in the next matching rounds again
+    Include { participant_id: u32 },
+    /// Move a participant into another group, which is created if it doesn't exist yet
+    Move { participant_id: u32, group: i32 },
+}
+
+fn main() -> ExitCode {
+    let Args {
+        command,
+        data_path,
+        strict: _,
+        lenient,
+    } = Args::parse();
+
+    let data_source = DataSource {
+        data_path,
+        mode: match lenient {
+            true => ValidationMode::Lenient,
+            false => ValidationMode::Strict,
+        },
+    };
 
+    match run(command, &data_source) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("\n### Error ###\n\n{error}");
+            error.exit_code()
+        }
+    }
+}
+
+fn run(command: Commands, data_source: &DataSource) -> Result<(), Error> {
     match command {
         Commands::PastMatchMessages {
             matching_round_id,
             intervall_weeks,
-        } => print_messages_for_past_round(matching_round_id, intervall_weeks, &data_path),
-        Commands::PastRoundSummary { matching_round_id } => {
-            print_summary_for_past_round(matching_round_id, &data_path)
-        }
-        Commands::CompleteGivers {} => print_complete_givers(&data_path),
+            message_output,
+        } => print_messages_for_past_round(
+            matching_round_id,
+            intervall_weeks,
+            &message_output,
+            data_source,
+        ),
+        Commands::PastRoundSummary {
+            matching_round_id,
+            rescore,
+        } => print_summary_for_past_round(matching_round_id, rescore, data_source),
+        Commands::CompleteGivers {} => print_complete_givers(data_source),
+        Commands::Stats { format } => print_stats(format, data_source),
+        Commands::Report { output, as_of } => write_report_for_data_source(
+            output,
+            as_of.unwrap_or_else(|| OffsetDateTime::now_utc().date()),
+            data_source,
+        ),
         Commands::CreateMatch {
             json_save: save_json,
             messages_generate: generate_messages,
+            message_output,
             intervall_weeks,
             cross_team_round,
+            strategy,
+            tries,
+            seed,
+            reciprocal_matches,
+            reverse_matches_as_recent,
+            objective,
+            score_floor,
+            as_of,
         } => create_match(
             generate_messages,
+            &message_output,
             save_json,
             intervall_weeks,
-            cross_team_round,
-            &data_path,
+            &MatchingOptions {
+                cross_team_round,
+                strategy,
+                number_of_tries: tries,
+                seed: seed.unwrap_or_else(rand::random),
+                reciprocal_matches,
+                reverse_matches_as_recent,
+                objective,
+                score_floor,
+                as_of: as_of.unwrap_or_else(|| OffsetDateTime::now_utc().date()),
+            },
+            data_source,
+        ),
+        Commands::DeleteMatch {} => delete_match(data_source),
+        Commands::CalculateAndSaveScores { as_of } => calculate_and_save_scores(
+            as_of.unwrap_or_else(|| OffsetDateTime::now_utc().date()),
+            data_source,
         ),
-        Commands::DeleteMatch {} => delete_match(&data_path),
-        Commands::CalculateAndSaveScores {} => calculate_and_save_scores(&data_path),
         Commands::AddGroupIdsToPastMatchParticipants {} => {
-            add_group_ids_to_past_match_participants(&data_path)
+            add_group_ids_to_past_match_participants(data_source)
+        }
+        Commands::AddFeedbackDatesToPastMatchingRounds { intervall_weeks } => {
+            add_feedback_dates_to_past_matching_rounds(intervall_weeks, data_source)
+        }
+        Commands::RestoreBackup { backup_name } => {
+            restore_backup_for_data_source(backup_name, data_source)
+        }
+        Commands::ImportJsonData { json_data_path } => {
+            import_json_data(&json_data_path, data_source)
+        }
+        Commands::Import { csv_path, dry_run } => {
+            import_csv(&csv_path, dry_run, data_source.open_storage()?.as_ref())
+        }
+        Commands::Export { csv_path } => {
+            export_participants_for_data_source(&csv_path, data_source)
         }
+        Commands::ExportHistory {
+            csv_path,
+            matrix_directory,
+            matrix_value,
+            matrix_format,
+        } => export_history_for_data_source(
+            &csv_path,
+            matrix_directory,
+            matrix_value,
+            matrix_format,
+            data_source,
+        ),
+        Commands::Participant { command } => manage_participants(command, data_source),
+        Commands::ExportCalendar {
+            matching_round_id,
+            intervall_weeks,
+            start_time,
+            duration_minutes,
+            per_round,
+            directory,
+        } => export_calendar_for_round(
+            matching_round_id,
+            intervall_weeks,
+            &CalendarOptions {
+                start_time,
+                duration_minutes,
+                per_round,
+            },
+            directory,
+            data_source,
+        ),
+        Commands::DeliverMessages {
+            matching_round_id,
+            intervall_weeks,
+            channel,
+            dry_run,
+            force,
+        } => deliver_messages_for_round(
+            matching_round_id,
+            intervall_weeks,
+            channel,
+            dry_run,
+            force,
+            data_source,
+        ),
     }
 }
 
-fn matches_file_path(data_path: &String) -> String {
-    format!("{}/matches.json", data_path)
+fn read_scoring_config_for_data_source(data_source: &DataSource) -> Result<ScoringConfig, Error> {
+    read_scoring_config(
+        &data_source.config_file_path("scoring.json"),
+        data_source.mode,
+    )
 }
 
-fn participants_file_path(data_path: &String) -> String {
-    format!("{}/participants.json", data_path)
+/// Finds the matching round with the id, or the last matching round if no id is given
+fn find_matching_round(
+    matching_rounds: &[MatchingRound],
+    matching_round_id: Option<i32>,
+) -> Result<&MatchingRound, Error> {
+    match matching_round_id {
+        None => matching_rounds
+            .last()
+            .ok_or_else(|| Error::NotFound("No matches have been created yet.".to_string())),
+        Some(matching_round_id) => matching_rounds
+            .iter()
+            .find(|r| r.id == matching_round_id)
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "No matching round with id {matching_round_id} has been found"
+                ))
+            }),
+    }
 }
 
 fn print_messages_for_past_round(
     matching_round_id: Option<i32>,
-    intervall_weeks: i32,
-    data_path: &String,
-) {
-    let past_matching_rounds = read_matching_rounds(&matches_file_path(data_path));
+    intervall_weeks: Option<i32>,
+    message_output: &MessageOutputArgs,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let participants_file = storage.read_participants()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    let matching_round = find_matching_round(&past_matching_rounds, matching_round_id)?;
+    let templates = MessageTemplates::load(&data_source.directory())?;
+    let (matching_round, intervall_weeks) = with_intervall_weeks(
+        matching_round,
+        intervall_weeks,
+        &participants_file,
+        data_source,
+    )?;
 
-    match matching_round_id {
-        None => {
-            let last_matching_round = past_matching_rounds.last();
-
-            match last_matching_round {
-                Some(matching_round) => {
-                    print_messages_for_round(matching_round, intervall_weeks);
-                }
-                None => {
-                    println!("No matches have been created yet.");
-                }
-            };
+    print_messages_for_round(
+        &matching_round,
+        intervall_weeks,
+        &templates,
+        &message_output.for_round(&matching_round, &participants_file, data_source),
+    )
+}
+
+/// The round with the feedback dates of the interval and the interval, an explicit interval wins
+/// over the one recorded with the round
+fn with_intervall_weeks(
+    matching_round: &MatchingRound,
+    intervall_weeks: Option<i32>,
+    participants_file: &ParticipantsFile,
+    data_source: &DataSource,
+) -> Result<(MatchingRound, i32), Error> {
+    let intervall_weeks = intervall_weeks
+        .or(matching_round.intervall_weeks)
+        .unwrap_or(DEFAULT_INTERVALL_WEEKS);
+    let calendar = read_holiday_calendar(
+        &data_source.config_file_path("holidays.json"),
+        data_source.mode,
+    )?;
+
+    let matching_round = MatchingRound {
+        feedback_dates: get_feedback_dates_for_intervall(
+            matching_round,
+            intervall_weeks,
+            participants_file,
+            &calendar,
+        ),
+        ..matching_round.clone()
+    };
+
+    Ok((matching_round, intervall_weeks))
+}
+
+fn export_history_for_data_source(
+    csv_path: &str,
+    matrix_directory: Option<String>,
+    matrix_value: MatrixValue,
+    matrix_format: MatrixFormat,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    export_history(csv_path, &past_matching_rounds)?;
+    println!("Exported the history to {csv_path}");
+
+    if let Some(directory) = matrix_directory {
+        let participants_file = storage.read_participants()?;
+        let file_paths = export_matrices(
+            &participants_file,
+            &past_matching_rounds,
+            matrix_value,
+            matrix_format,
+            &directory,
+        )?;
+        println!("Exported {} matrices to {directory}", file_paths.len());
+    }
+
+    Ok(())
+}
+
+fn export_participants_for_data_source(
+    csv_path: &str,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let participants_file = data_source.open_storage()?.read_participants()?;
+    write_participants_csv(csv_path, &participants_file)?;
+    println!("Exported the participants to {csv_path}");
+
+    Ok(())
+}
+
+fn manage_participants(
+    command: ParticipantCommands,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let mut participants_file = storage.read_participants()?;
+
+    // The outcome is only printed once the participants have been saved
+    let outcome = match command {
+        ParticipantCommands::List {} => {
+            print_participants(&participants_file);
+            return Ok(());
         }
-        Some(matching_round_id) => {
-            let matching_round = past_matching_rounds
+        ParticipantCommands::Add {
+            first_name,
+            last_name,
+            gender,
+            group,
+            email,
+            locale,
+            excluded,
+        } => {
+            let past_matching_rounds = storage.read_matching_rounds()?;
+            let used_ids = past_matching_rounds
                 .iter()
-                .find(|r| r.id == matching_round_id);
-
-            match matching_round {
-                Some(matching_round) => {
-                    print_messages_for_round(matching_round, intervall_weeks);
-                }
-                None => {
-                    println!("No matching round with id {matching_round_id} has been found");
-                }
+                .flat_map(|r| r.matches.iter())
+                .flat_map(|m| [m.giver.id, m.receiver.id]);
+
+            let participant = Participant {
+                id: participants_file.next_participant_id(used_ids),
+                first_name,
+                last_name,
+                gender,
+                pronouns: None,
+                locale,
+                email,
+                webhook_url: None,
             };
+
+            let outcome = format!(
+                "Added {} with id {} to group {group}",
+                participant.full_name(),
+                participant.id
+            );
+            participants_file.add_participant(group, participant, excluded);
+            outcome
+        }
+        ParticipantCommands::Remove { participant_id } => {
+            let (participant, _) = participants_file.remove_participant(participant_id)?;
+            format!("Removed {}", participant.full_name())
+        }
+        ParticipantCommands::Exclude { participant_id } => {
+            participants_file.set_excluded(participant_id, true)?;
+            format!("Excluded participant {participant_id}")
+        }
+        ParticipantCommands::Include { participant_id } => {
+            participants_file.set_excluded(participant_id, false)?;
+            format!("Included participant {participant_id}")
+        }
+        ParticipantCommands::Move {
+            participant_id,
+            group,
+        } => {
+            participants_file.move_participant(participant_id, group)?;
+            format!("Moved participant {participant_id} to group {group}")
+        }
+    };
+
+    storage.save_participants(&participants_file)?;
+    println!("{outcome}");
+
+    Ok(())
+}
+
+fn print_participants(participants_file: &ParticipantsFile) {
+    for group in &participants_file.groups {
+        println!("\nGroup {}:", group.id);
+
+        for participant in &group.participants {
+            println!("  {:>4} {}", participant.id, participant.full_name());
+        }
+
+        for participant in &group.excluded_participants {
+            println!(
+                "  {:>4} {} (excluded)",
+                participant.id,
+                participant.full_name()
+            );
         }
     }
 }
 
-fn print_summary_for_past_round(matching_round_id: Option<i32>, data_path: &String) {
-    let past_matching_rounds = read_matching_rounds(&matches_file_path(data_path));
+fn parse_time(time: &str) -> Result<Time, String> {
+    Time::parse(time, format_description!("[hour]:[minute]")).map_err(|e| e.to_string())
+}
 
-    match matching_round_id {
-        None => {
-            let last_matching_round = past_matching_rounds.last();
-
-            match last_matching_round {
-                Some(matching_round) => {
-                    print_result(matching_round);
-                }
-                None => {
-                    println!("No matches have been created yet.");
-                }
-            };
+fn parse_date(date: &str) -> Result<Date, String> {
+    Date::parse(date, format_description!("[year]-[month]-[day]")).map_err(|e| e.to_string())
+}
+
+fn export_calendar_for_round(
+    matching_round_id: Option<i32>,
+    intervall_weeks: Option<i32>,
+    options: &CalendarOptions,
+    directory: Option<String>,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let participants_file = storage.read_participants()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    let matching_round = find_matching_round(&past_matching_rounds, matching_round_id)?;
+    let (matching_round, intervall_weeks) = with_intervall_weeks(
+        matching_round,
+        intervall_weeks,
+        &participants_file,
+        data_source,
+    )?;
+    let directory = directory.unwrap_or_else(|| {
+        format!(
+            "{}/messages/round_{}",
+            data_source.directory(),
+            matching_round.id
+        )
+    });
+
+    let file_paths = export_calendar(
+        &matching_round,
+        intervall_weeks,
+        &participants_file.get_emails(),
+        options,
+        &directory,
+    )?;
+    println!(
+        "Wrote {} calendar files:\n{}",
+        file_paths.len(),
+        format_list(&file_paths)
+    );
+
+    Ok(())
+}
+
+fn deliver_messages_for_round(
+    matching_round_id: Option<i32>,
+    intervall_weeks: Option<i32>,
+    channel: DeliveryChannel,
+    dry_run: bool,
+    force: bool,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let participants_file = storage.read_participants()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    let matching_round = find_matching_round(&past_matching_rounds, matching_round_id)?;
+    let templates = MessageTemplates::load(&data_source.directory())?;
+    let delivery_config = read_delivery_config(
+        &data_source.config_file_path("delivery.json"),
+        data_source.mode,
+    )?;
+    let delivery_log_path =
+        data_source.config_file_path(&DeliveryLog::file_name(matching_round.id));
+    let mut delivery_log =
+        read_delivery_log(&delivery_log_path, matching_round.id, data_source.mode)?;
+
+    let (matching_round, intervall_weeks) = with_intervall_weeks(
+        matching_round,
+        intervall_weeks,
+        &participants_file,
+        data_source,
+    )?;
+    let messages = generate_messages(&matching_round, intervall_weeks, &templates);
+
+    let errors = match channel {
+        DeliveryChannel::Webhook => {
+            // The current contact details are used, they may have changed since the round was
+            // created
+            let webhook_urls: HashMap<u32, String> = participants_file
+                .all_participants()
+                .filter_map(|p| p.webhook_url.clone().map(|url| (p.id, url)))
+                .collect();
+
+            deliver_messages(
+                &messages,
+                &webhook_urls,
+                &delivery_config.webhook,
+                &mut delivery_log,
+                |log| save_delivery_log(&delivery_log_path, log),
+                dry_run,
+                force,
+            )?
         }
-        Some(matching_round_id) => {
-            let matching_round = past_matching_rounds
-                .iter()
-                .find(|r| r.id == matching_round_id);
-
-            match matching_round {
-                Some(matching_round) => {
-                    print_result(matching_round);
-                }
-                None => {
-                    println!("No matching round with id {matching_round_id} has been found");
-                }
-            };
+        DeliveryChannel::Email => {
+            let smtp_config = delivery_config.smtp.as_ref().ok_or_else(|| {
+                Error::NotFound("No SMTP relay has been configured in delivery.json".to_string())
+            })?;
+
+            send_emails(
+                &messages,
+                &participants_file.get_emails(),
+                smtp_config,
+                &mut delivery_log,
+                |log| save_delivery_log(&delivery_log_path, log),
+                dry_run,
+                force,
+            )?
         }
+    };
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(Error::Delivery(errors)),
+    }
+}
+
+fn print_summary_for_past_round(
+    matching_round_id: Option<i32>,
+    rescore: bool,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let mut past_matching_rounds = data_source.open_storage()?.read_matching_rounds()?;
+
+    if rescore {
+        let scoring_config = read_scoring_config_for_data_source(data_source)?;
+        past_matching_rounds = calculate_scores(&past_matching_rounds, &scoring_config, None);
     }
+
+    print_result(find_matching_round(
+        &past_matching_rounds,
+        matching_round_id,
+    )?);
+
+    Ok(())
+}
+
+fn write_report_for_data_source(
+    output: Option<String>,
+    as_of: Date,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let participants_file = storage.read_participants()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    let scoring_config = read_scoring_config_for_data_source(data_source)?;
+    let file_path = output.unwrap_or_else(|| data_source.config_file_path("report.html"));
+
+    write_html_report(
+        &file_path,
+        &participants_file,
+        &past_matching_rounds,
+        &scoring_config,
+        as_of,
+    )?;
+    println!("Wrote the report to {file_path}");
+
+    Ok(())
 }
 
-fn print_complete_givers(data_path: &String) {
-    let participants_file = read_participants(&participants_file_path(data_path));
-    let past_matching_rounds = read_matching_rounds(&matches_file_path(data_path));
+fn print_complete_givers(data_source: &DataSource) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let participants_file = storage.read_participants()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
 
     let complete_givers_by_group = get_complete_givers(&participants_file, &past_matching_rounds);
 
-    for group_id in complete_givers_by_group.keys() {
-        let complete_givers = complete_givers_by_group
-            .get(group_id)
-            .expect("Complete givers not found");
-        let complete_giver_names: Vec<String> =
-            complete_givers.iter().map(|g| g.full_name()).collect();
-
-        println!(
-            "Complete givers for group {group_id} {:#?}",
-            complete_giver_names
-        );
+    for group in &participants_file.groups {
+        let complete_giver_names: Vec<String> = complete_givers_by_group[&group.id]
+            .iter()
+            .map(|g| g.full_name())
+            .collect();
+
+        match complete_giver_names.is_empty() {
+            true => println!("Complete givers for group {}: none", group.id),
+            false => println!(
+                "Complete givers for group {}:\n{}",
+                group.id,
+                format_list(&complete_giver_names)
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// One indented line per item, for the lists printed to the console
+fn format_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("  - {item}"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn print_stats(format: StatsFormat, data_source: &DataSource) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let participants_file = storage.read_participants()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+
+    let stats = get_stats(&participants_file, &past_matching_rounds);
+
+    match format {
+        StatsFormat::Text => print!("{stats}"),
+        StatsFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&stats).expect("Failed to serialize the stats")
+        ),
     }
 
-    // println!("{:#?}", complete_givers_by_group);
+    Ok(())
 }
 
 fn create_match(
     generate_messages: bool,
+    message_output: &MessageOutputArgs,
     save_json: bool,
     intervall_weeks: i32,
-    cross_team_round: bool,
-    data_path: &String,
-) {
-    // Read JSON Data
-    let participants_file = read_participants(&participants_file_path(data_path));
-    let past_matching_rounds = read_matching_rounds(&matches_file_path(data_path));
+    matching_options: &MatchingOptions,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    // Read Data
+    let storage = data_source.open_storage()?;
+    let participants_file = storage.read_participants()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    let scoring_config = read_scoring_config_for_data_source(data_source)?;
+    let templates = MessageTemplates::load(&data_source.directory())?;
+    let calendar = read_holiday_calendar(
+        &data_source.config_file_path("holidays.json"),
+        data_source.mode,
+    )?;
+
+    let constraints =
+        MatchingConstraints::new(&participants_file, matching_options.cross_team_round)
+            .map_err(Error::Constraints)?;
 
     // Match participants
-    let mut rng = ChaCha8Rng::from_entropy();
-    let matching_round = match_participants(
+    let mut matching_round = match_participants(
         &participants_file,
         &past_matching_rounds,
-        cross_team_round,
-        &mut rng,
+        matching_options,
+        &constraints,
+        &scoring_config,
+    );
+    matching_round.intervall_weeks = Some(intervall_weeks);
+    matching_round.feedback_dates = get_feedback_dates(
+        matching_round.date,
+        intervall_weeks,
+        &participants_file,
+        &calendar,
     );
 
     // Print messages
     if generate_messages {
         println!("\n### Messages: ###");
-        print_messages_for_round(&matching_round, intervall_weeks);
+        print_messages_for_round(
+            &matching_round,
+            intervall_weeks,
+            &templates,
+            &message_output.for_round(&matching_round, &participants_file, data_source),
+        )?;
     }
 
     print_result(&matching_round);
 
-    // Save matches to JSON file
+    // Save matches to the storage
     if save_json {
-        save_matching_round(&matches_file_path(data_path), matching_round);
+        storage.save_matching_round(matching_round)?;
     }
+
+    Ok(())
 }
 
 fn print_result(matching_round: &MatchingRound) {
     println!("\n### Result: ###\n");
 
+    if let Some(settings_info) = get_settings_info(matching_round) {
+        println!("{settings_info}\n");
+    }
+
     for group_match in &matching_round.matches {
         let giver_name = group_match.giver.full_name();
-        let giver_group = group_match.giver.group_id;
         let receiver_name = group_match.receiver.full_name();
-        let receiver_group = group_match.receiver.group_id;
         let score = group_match.score;
-        let switch_info = match giver_group != receiver_group {
-            true => {
-                format!("Cross-Team Match between {giver_group} and {receiver_group}")
-            }
-            false => format!("In-Team Match in group {giver_group}"),
-        };
+        let switch_info = get_switch_info(group_match);
         println!("{switch_info}: {giver_name} => {receiver_name}, score: {score}");
     }
+
+    print_score_statistics(&matching_round.matches);
+}
+
+fn print_score_statistics(matches: &[Match]) {
+    let Some(ScoreRange { min, mean, max }) = get_score_range(matches) else {
+        return;
+    };
+
+    let distribution: Vec<String> = get_score_counts(matches)
+        .iter()
+        .map(|(score, count)| format!("{score} x {count}"))
+        .collect();
+
+    println!("\nLowest score: {min}, average score: {mean}, highest score: {max}");
+    println!("Score distribution: {}", distribution.join(", "));
+    println!("Reciprocal matches: {}", count_reciprocal_matches(matches));
 }
 
-fn delete_match(data_path: &String) {
-    let mut past_matching_rounds = read_matching_rounds(&matches_file_path(data_path));
-    past_matching_rounds.pop();
-    update_all_existing_rounds(&matches_file_path(data_path), &past_matching_rounds);
+fn delete_match(data_source: &DataSource) -> Result<(), Error> {
+    data_source.open_storage()?.delete_last_matching_round()
 }
 
-fn calculate_and_save_scores(data_path: &String) {
-    let past_matching_rounds = read_matching_rounds(&matches_file_path(data_path));
-    let new_matching_rounds = calculate_scores(&past_matching_rounds);
-    update_all_existing_rounds(&matches_file_path(data_path), &new_matching_rounds);
+fn calculate_and_save_scores(as_of: Date, data_source: &DataSource) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    let scoring_config = read_scoring_config_for_data_source(data_source)?;
+    let new_matching_rounds = calculate_scores(&past_matching_rounds, &scoring_config, Some(as_of));
+    storage.update_all_existing_rounds(&new_matching_rounds)
 }
 
-fn add_group_ids_to_past_match_participants(data_path: &String) {
-    let past_matching_rounds = read_matching_rounds(&matches_file_path(data_path));
-    let participants_file = read_participants(&participants_file_path(data_path));
+fn add_group_ids_to_past_match_participants(data_source: &DataSource) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    let participants_file = storage.read_participants()?;
 
     let new_matching_rounds =
         update_matching_rounds_with_group_ids(&past_matching_rounds, &participants_file);
 
-    update_all_existing_rounds(&matches_file_path(data_path), &new_matching_rounds);
+    storage.update_all_existing_rounds(&new_matching_rounds)
+}
+
+fn add_feedback_dates_to_past_matching_rounds(
+    intervall_weeks: i32,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let storage = data_source.open_storage()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+
+    let new_matching_rounds =
+        update_matching_rounds_with_feedback_dates(&past_matching_rounds, intervall_weeks);
+
+    storage.update_all_existing_rounds(&new_matching_rounds)
+}
+
+fn restore_backup_for_data_source(
+    backup_name: Option<String>,
+    data_source: &DataSource,
+) -> Result<(), Error> {
+    let data_directory = data_source.directory();
+
+    let Some(backup_name) = backup_name else {
+        let backup_names = list_backups(&data_directory);
+
+        match backup_names.is_empty() {
+            true => println!("No backups have been created yet."),
+            false => println!("Backups, oldest first:\n{}", format_list(&backup_names)),
+        }
+
+        return Ok(());
+    };
+
+    let file_path = restore_backup(&data_directory, &backup_name)?;
+    println!("Restored {file_path} from the backup {backup_name}");
+
+    Ok(())
+}
+
+fn import_json_data(json_data_path: &str, data_source: &DataSource) -> Result<(), Error> {
+    let json_storage = JsonStorage::new(json_data_path, data_source.mode);
+    let storage = data_source.open_storage()?;
+
+    let participants_file = json_storage.read_participants()?;
+    let matching_rounds = json_storage.read_matching_rounds()?;
+
+    storage.save_participants(&participants_file)?;
+    storage.update_all_existing_rounds(&matching_rounds)?;
+
+    println!(
+        "Imported {} groups and {} matching rounds from {json_data_path}",
+        participants_file.groups.len(),
+        matching_rounds.len()
+    );
+
+    Ok(())
 }
@@ -0,0 +1,221 @@
+/// Solves the assignment problem for a (possibly rectangular) weight matrix, where
+/// `weights[giver][receiver]` is `None` if the pair must not be matched.
+///
+/// The result contains the assigned receiver index for every giver. The number of assigned
+/// pairs is maximized first and the sum of their weights second, so a giver is only left
+/// without a receiver if there is no way to match them without unmatching someone else.
+pub fn solve_assignment(weights: &[Vec<Option<i64>>]) -> Vec<Option<usize>> {
+    let number_of_givers = weights.len();
+    let number_of_receivers = weights.iter().map(|row| row.len()).max().unwrap_or(0);
+    let size = number_of_givers.max(number_of_receivers);
+
+    if size == 0 {
+        return Vec::new();
+    }
+
+    let max_weight = weights
+        .iter()
+        .flatten()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let min_weight = weights
+        .iter()
+        .flatten()
+        .flatten()
+        .copied()
+        .min()
+        .unwrap_or(0);
+
+    // Allowed pairs cost between 0 and the weight range, forbidden pairs cost more than any
+    // combination of allowed pairs, so the number of allowed pairs is maximized first.
+    let weight_range = max_weight - min_weight;
+    let forbidden_cost = (weight_range + 1) * (size as i64 + 1);
+
+    let cost = |giver: usize, receiver: usize| -> i64 {
+        match weights.get(giver).and_then(|row| row.get(receiver)) {
+            Some(Some(weight)) => max_weight - weight,
+            _ => forbidden_cost,
+        }
+    };
+
+    let (assigned_givers, _) = hungarian(size, cost);
+
+    let mut assignment = vec![None; number_of_givers];
+
+    for (receiver, giver) in assigned_givers.into_iter().enumerate() {
+        if giver >= number_of_givers {
+            continue;
+        }
+
+        if let Some(Some(_)) = weights[giver].get(receiver) {
+            assignment[giver] = Some(receiver);
+        }
+    }
+
+    assignment
+}
+
+/// Minimum cost perfect matching on a square cost matrix. Returns the assigned column of every
+/// row and the pairs with a reduced cost of zero: every perfect matching that only uses these
+/// pairs has the minimum cost as well.
+pub fn solve_square_assignment(costs: &[Vec<i64>]) -> (Vec<usize>, Vec<Vec<bool>>) {
+    let size = costs.len();
+    let (assigned_rows, reduced_cost) = hungarian(size, |row, column| costs[row][column]);
+
+    let mut assignment = vec![0; size];
+    for (column, row) in assigned_rows.into_iter().enumerate() {
+        assignment[row] = column;
+    }
+
+    let tight_pairs = (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|column| reduced_cost(row, column) == 0)
+                .collect()
+        })
+        .collect();
+
+    (assignment, tight_pairs)
+}
+
+/// Minimum cost perfect matching on a square `size` x `size` matrix using the Hungarian
+/// algorithm with potentials. Returns the assigned row for every column and the reduced cost
+/// of the pairs under the final potentials.
+fn hungarian(
+    size: usize,
+    cost: impl Fn(usize, usize) -> i64,
+) -> (Vec<usize>, impl Fn(usize, usize) -> i64) {
+    let infinity = i64::MAX / 4;
+
+    // Rows and columns are 1-indexed, index 0 is used as a virtual starting column
+    let mut row_potentials = vec![0i64; size + 1];
+    let mut column_potentials = vec![0i64; size + 1];
+    let mut column_assignments = vec![0usize; size + 1];
+    let mut way = vec![0usize; size + 1];
+
+    for row in 1..=size {
+        column_assignments[0] = row;
+        let mut current_column = 0;
+        let mut min_values = vec![infinity; size + 1];
+        let mut used = vec![false; size + 1];
+
+        loop {
+            used[current_column] = true;
+            let current_row = column_assignments[current_column];
+            let mut delta = infinity;
+            let mut next_column = 0;
+
+            for column in 1..=size {
+                if used[column] {
+                    continue;
+                }
+
+                let reduced_cost = cost(current_row - 1, column - 1)
+                    - row_potentials[current_row]
+                    - column_potentials[column];
+
+                if reduced_cost < min_values[column] {
+                    min_values[column] = reduced_cost;
+                    way[column] = current_column;
+                }
+
+                if min_values[column] < delta {
+                    delta = min_values[column];
+                    next_column = column;
+                }
+            }
+
+            for column in 0..=size {
+                if used[column] {
+                    row_potentials[column_assignments[column]] += delta;
+                    column_potentials[column] -= delta;
+                } else {
+                    min_values[column] -= delta;
+                }
+            }
+
+            current_column = next_column;
+
+            if column_assignments[current_column] == 0 {
+                break;
+            }
+        }
+
+        loop {
+            let previous_column = way[current_column];
+            column_assignments[current_column] = column_assignments[previous_column];
+            current_column = previous_column;
+
+            if current_column == 0 {
+                break;
+            }
+        }
+    }
+
+    let assigned_rows = column_assignments[1..].iter().map(|row| row - 1).collect();
+    let reduced_cost = move |row: usize, column: usize| {
+        cost(row, column) - row_potentials[row + 1] - column_potentials[column + 1]
+    };
+
+    (assigned_rows, reduced_cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_weight(weights: &[Vec<Option<i64>>], assignment: &[Option<usize>]) -> i64 {
+        assignment
+            .iter()
+            .enumerate()
+            .filter_map(|(giver, receiver)| receiver.and_then(|r| weights[giver][r]))
+            .sum()
+    }
+
+    #[test]
+    fn test_solve_assignment_finds_best_derangement() {
+        let weights = vec![
+            vec![None, Some(10), Some(1)],
+            vec![Some(1), None, Some(10)],
+            vec![Some(10), Some(1), None],
+        ];
+
+        let assignment = solve_assignment(&weights);
+
+        assert_eq!(assignment, vec![Some(1), Some(2), Some(0)]);
+        assert_eq!(total_weight(&weights, &assignment), 30);
+    }
+
+    #[test]
+    fn test_solve_assignment_prefers_more_matches_over_higher_weights() {
+        // Matching 0 => 0 alone has the highest weight, but leaves giver 1 unmatched
+        let weights = vec![vec![Some(1000), Some(1)], vec![Some(1), None]];
+
+        let assignment = solve_assignment(&weights);
+
+        assert_eq!(assignment, vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn test_solve_assignment_rectangular() {
+        let weights = vec![
+            vec![Some(5), Some(7)],
+            vec![Some(6), Some(1)],
+            vec![None, Some(9)],
+        ];
+
+        let assignment = solve_assignment(&weights);
+
+        assert_eq!(assignment.iter().filter(|r| r.is_some()).count(), 2);
+        assert_eq!(total_weight(&weights, &assignment), 15);
+    }
+
+    #[test]
+    fn test_solve_assignment_without_any_allowed_pair() {
+        let weights = vec![vec![None]];
+
+        assert_eq!(solve_assignment(&weights), vec![None]);
+    }
+}
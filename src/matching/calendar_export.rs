@@ -0,0 +1,223 @@
+use super::message_export::get_file_name;
+use super::messages::get_feedback_date;
+use crate::error::Error;
+use crate::file_io::write_atomically;
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participant::MatchParticipant;
+use crate::structs::r#match::Match;
+
+use std::collections::HashMap;
+use std::fs;
+use time::macros::format_description;
+use time::{Duration, OffsetDateTime, PrimitiveDateTime, Time};
+
+/// When the feedback conversations take place on the feedback date
+#[derive(Debug, Clone)]
+pub struct CalendarOptions {
+    pub start_time: Time,
+    pub duration_minutes: i64,
+    /// Write all events into one file instead of one file per match
+    pub per_round: bool,
+}
+
+/// Writes the feedback conversation of every match as a calendar event into the directory and
+/// returns the paths of the written files. The participants with a current email address are
+/// invited as attendees.
+pub fn export_calendar(
+    matching_round: &MatchingRound,
+    intervall_weeks: i32,
+    emails: &HashMap<u32, String>,
+    options: &CalendarOptions,
+    directory: &str,
+) -> Result<Vec<String>, Error> {
+    fs::create_dir_all(directory).map_err(|e| Error::io(directory, e))?;
+
+    let timestamp = OffsetDateTime::now_utc();
+    let events: Vec<(&Match, Vec<String>)> = matching_round
+        .matches
+        .iter()
+        .map(|m| {
+            let feedback_date =
+                get_feedback_date(matching_round, m.giver.group_id, intervall_weeks);
+            let start = PrimitiveDateTime::new(feedback_date, options.start_time);
+            let end = start + Duration::minutes(options.duration_minutes);
+
+            (
+                m,
+                create_event(matching_round.id, m, emails, start, end, timestamp),
+            )
+        })
+        .collect();
+
+    if options.per_round {
+        let file_path = format!("{directory}/feedback_round_{}.ics", matching_round.id);
+        let lines = events.into_iter().flat_map(|(_, event)| event).collect();
+        write_atomically(&file_path, &create_calendar(lines))?;
+
+        return Ok(vec![file_path]);
+    }
+
+    events
+        .into_iter()
+        .map(|(m, event)| {
+            let file_path = format!("{directory}/{}.ics", get_file_name(&m.giver));
+            write_atomically(&file_path, &create_calendar(event))?;
+
+            Ok(file_path)
+        })
+        .collect()
+}
+
+fn create_calendar(event_lines: Vec<String>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//feedback-matching//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+    lines.extend(event_lines);
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<String>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+/// The times have no time zone, so calendars import them in the local time of the participants
+fn create_event(
+    round_id: i32,
+    group_match: &Match,
+    emails: &HashMap<u32, String>,
+    start: PrimitiveDateTime,
+    end: PrimitiveDateTime,
+    timestamp: OffsetDateTime,
+) -> Vec<String> {
+    let giver = &group_match.giver;
+    let receiver = &group_match.receiver;
+    let local_format = format_description!("[year][month][day]T[hour][minute][second]");
+    let format = |date_time: PrimitiveDateTime| {
+        date_time
+            .format(local_format)
+            .expect("Failed to format the date")
+    };
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!(
+            "UID:round-{round_id}-{}-{}@feedback-matching",
+            giver.id, receiver.id
+        ),
+        format!(
+            "DTSTAMP:{}Z",
+            format(PrimitiveDateTime::new(timestamp.date(), timestamp.time()))
+        ),
+        format!("DTSTART:{}", format(start)),
+        format!("DTEND:{}", format(end)),
+        format!(
+            "SUMMARY:{}",
+            escape_text(
+                &giver
+                    .get_locale()
+                    .feedback_event_title(&giver.full_name(), &receiver.full_name())
+            )
+        ),
+    ];
+
+    lines.extend(
+        [giver, receiver]
+            .into_iter()
+            .filter_map(|participant| attendee(participant, emails.get(&participant.id))),
+    );
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+/// Attendees need an address, participants without email address are only named in the title
+fn attendee(participant: &MatchParticipant, email: Option<&String>) -> Option<String> {
+    email.map(|email| {
+        format!(
+            "ATTENDEE;CN=\"{}\";ROLE=REQ-PARTICIPANT:mailto:{email}",
+            participant.full_name().replace('"', "'")
+        )
+    })
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Lines longer than 75 bytes continue on the next line after a space (RFC 5545)
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_length = 0;
+
+    for c in line.chars() {
+        if line_length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+
+        folded.push(c);
+        line_length += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::locale::Locale;
+    use crate::test_fixtures::match_participant;
+    use time::macros::datetime;
+
+    fn participant(id: u32, last_name: &str) -> MatchParticipant {
+        MatchParticipant {
+            first_name: "Anna".to_string(),
+            last_name: last_name.to_string(),
+            locale: Some(Locale::En),
+            ..match_participant(id, 1)
+        }
+    }
+
+    #[test]
+    fn test_event() {
+        let group_match = Match {
+            giver: participant(1, "Alt, Jr."),
+            receiver: participant(2, "Bauer"),
+            score: 0,
+        };
+
+        assert_eq!(
+            create_event(
+                3,
+                &group_match,
+                &HashMap::from([(1, "anna@example.com".to_string())]),
+                datetime!(2024-03-14 10:00),
+                datetime!(2024-03-14 10:30),
+                datetime!(2024-03-01 08:15:30 UTC)
+            ),
+            vec![
+                "BEGIN:VEVENT",
+                "UID:round-3-1-2@feedback-matching",
+                "DTSTAMP:20240301T081530Z",
+                "DTSTART:20240314T100000",
+                "DTEND:20240314T103000",
+                "SUMMARY:Feedback from Anna Alt\\, Jr. to Anna Bauer",
+                "ATTENDEE;CN=\"Anna Alt, Jr.\";ROLE=REQ-PARTICIPANT:mailto:anna@example.com",
+                "END:VEVENT",
+            ]
+        );
+        assert_eq!(
+            fold_line(&"a".repeat(80)),
+            format!("{}\r\n {}", "a".repeat(75), "a".repeat(5))
+        );
+    }
+}
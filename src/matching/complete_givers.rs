@@ -1,26 +1,27 @@
 use crate::structs::matching_round::MatchingRound;
 use crate::structs::participant::Participant;
-use crate::structs::participants_file::{ParticipantsFile, ParticipantsGroup};
+use crate::structs::participants_file::{Constraints, ParticipantsFile, ParticipantsGroup};
 
 use std::collections::HashMap;
 
-use super::last_match_map::get_last_match_map;
+use super::last_match_map::get_match_count_map;
 
+/// The givers of every group who have been matched with every receiver of the group they may be
+/// matched with, forbidden pairs don't count as open
 pub fn get_complete_givers(
     participants_file: &ParticipantsFile,
-    past_matching_rounds: &Vec<MatchingRound>,
+    past_matching_rounds: &[MatchingRound],
 ) -> HashMap<i32, Vec<Participant>> {
-    let last_match_map = get_last_match_map(past_matching_rounds);
+    let match_count_map = get_match_count_map(past_matching_rounds);
     let groups = &participants_file.groups;
 
-    let complete_givers_per_group = get_complete_givers_per_group(&groups, &last_match_map);
-
-    complete_givers_per_group
+    get_complete_givers_per_group(groups, &participants_file.constraints, &match_count_map)
 }
 
 pub fn get_complete_givers_per_group(
-    groups: &Vec<ParticipantsGroup>,
-    last_match_map: &HashMap<(u32, u32), i64>,
+    groups: &[ParticipantsGroup],
+    constraints: &Constraints,
+    match_count_map: &HashMap<(u32, u32), u32>,
 ) -> HashMap<i32, Vec<Participant>> {
     groups
         .iter()
@@ -29,7 +30,8 @@ pub fn get_complete_givers_per_group(
                 group.id,
                 get_givers_who_have_matched_everyone_from_group(
                     &group.participants,
-                    &last_match_map,
+                    constraints,
+                    match_count_map,
                 ),
             )
         })
@@ -37,27 +39,31 @@ pub fn get_complete_givers_per_group(
 }
 
 fn get_givers_who_have_matched_everyone_from_group(
-    participants: &Vec<Participant>,
-    last_match_map: &HashMap<(u32, u32), i64>,
+    participants: &[Participant],
+    constraints: &Constraints,
+    match_count_map: &HashMap<(u32, u32), u32>,
 ) -> Vec<Participant> {
     participants
         .iter()
-        .filter(|giver| has_giver_matched_all_receivers(&last_match_map, giver, participants))
+        .filter(|giver| {
+            has_giver_matched_all_receivers(match_count_map, constraints, giver, participants)
+        })
         .cloned()
         .collect()
 }
 
 fn has_giver_matched_all_receivers(
-    last_match_map: &HashMap<(u32, u32), i64>,
+    match_count_map: &HashMap<(u32, u32), u32>,
+    constraints: &Constraints,
     giver: &Participant,
-    participants: &Vec<Participant>,
+    participants: &[Participant],
 ) -> bool {
     for receiver in participants {
-        if giver.id == receiver.id {
+        if giver.id == receiver.id || constraints.is_forbidden(giver.id, receiver.id) {
             continue;
         }
 
-        if !last_match_map.contains_key(&(giver.id, receiver.id)) {
+        if !match_count_map.contains_key(&(giver.id, receiver.id)) {
             return false;
         }
     }
@@ -0,0 +1,182 @@
+use crate::structs::participants_file::{PairConstraint, ParticipantsFile};
+
+use super::assignment::solve_assignment;
+use super::matcher::MatchingGroup;
+use std::collections::{HashMap, HashSet};
+
+/// The forbidden and required pairs of the participants file that apply to the next round
+#[derive(Debug, Clone, Default)]
+pub struct MatchingConstraints {
+    forbidden_pairs: HashSet<(u32, u32)>,
+    required_receivers: HashMap<u32, u32>,
+    required_givers: HashMap<u32, u32>,
+}
+
+impl MatchingConstraints {
+    /// Checks the constraints against the participants and returns every problem that makes
+    /// them impossible to fulfill. Required pairs with excluded participants are ignored.
+    pub fn new(
+        participants_file: &ParticipantsFile,
+        cross_team_round: bool,
+    ) -> Result<MatchingConstraints, Vec<String>> {
+        let participant_group_map: HashMap<u32, i32> = participants_file
+            .groups
+            .iter()
+            .flat_map(|g| g.participants.iter().map(|p| (p.id, g.id)))
+            .collect();
+        let known_ids: HashSet<u32> = participants_file
+            .groups
+            .iter()
+            .flat_map(|g| g.participants.iter().chain(g.excluded_participants.iter()))
+            .map(|p| p.id)
+            .collect();
+
+        let mut errors = Vec::new();
+        let mut constraints = MatchingConstraints::default();
+        let all_pairs = participants_file
+            .constraints
+            .forbidden_pairs
+            .iter()
+            .chain(participants_file.constraints.required_pairs.iter());
+
+        for pair in all_pairs {
+            for id in [pair.giver_id, pair.receiver_id] {
+                if !known_ids.contains(&id) {
+                    errors.push(format!(
+                        "{} references the unknown participant {id}",
+                        describe_pair(pair)
+                    ));
+                }
+            }
+        }
+
+        for pair in &participants_file.constraints.forbidden_pairs {
+            constraints
+                .forbidden_pairs
+                .insert((pair.giver_id, pair.receiver_id));
+        }
+
+        for pair in &participants_file.constraints.required_pairs {
+            let giver_group_id = participant_group_map.get(&pair.giver_id);
+            let receiver_group_id = participant_group_map.get(&pair.receiver_id);
+
+            let (Some(giver_group_id), Some(receiver_group_id)) =
+                (giver_group_id, receiver_group_id)
+            else {
+                continue;
+            };
+
+            if pair.giver_id == pair.receiver_id {
+                errors.push(format!(
+                    "{} pairs a participant with themself",
+                    describe_pair(pair)
+                ));
+            }
+
+            if constraints
+                .forbidden_pairs
+                .contains(&(pair.giver_id, pair.receiver_id))
+            {
+                errors.push(format!("{} is also forbidden", describe_pair(pair)));
+            }
+
+            if !cross_team_round && giver_group_id != receiver_group_id {
+                errors.push(format!(
+                    "{} is between groups {giver_group_id} and {receiver_group_id}, but this is not a cross-team round",
+                    describe_pair(pair)
+                ));
+            }
+
+            if let Some(receiver_id) = constraints
+                .required_receivers
+                .insert(pair.giver_id, pair.receiver_id)
+            {
+                errors.push(format!(
+                    "{} conflicts with the required receiver {receiver_id}",
+                    describe_pair(pair)
+                ));
+            }
+
+            if let Some(giver_id) = constraints
+                .required_givers
+                .insert(pair.receiver_id, pair.giver_id)
+            {
+                errors.push(format!(
+                    "{} conflicts with the required giver {giver_id}",
+                    describe_pair(pair)
+                ));
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(constraints),
+            false => Err(errors),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forbidden_pairs.is_empty() && self.required_receivers.is_empty()
+    }
+
+    pub fn is_allowed(&self, giver_id: u32, receiver_id: u32) -> bool {
+        if self.forbidden_pairs.contains(&(giver_id, receiver_id)) {
+            return false;
+        }
+
+        if let Some(required_receiver_id) = self.required_receivers.get(&giver_id) {
+            return *required_receiver_id == receiver_id;
+        }
+
+        if let Some(required_giver_id) = self.required_givers.get(&receiver_id) {
+            return *required_giver_id == giver_id;
+        }
+
+        true
+    }
+}
+
+fn describe_pair(pair: &PairConstraint) -> String {
+    format!("The pair {} => {}", pair.giver_id, pair.receiver_id)
+}
+
+/// The highest number of matches that is possible without breaking the constraints
+pub fn get_max_number_of_matches(
+    matching_groups: &[MatchingGroup],
+    constraints: &MatchingConstraints,
+    cross_team_round: bool,
+) -> usize {
+    let participant_sets = match cross_team_round {
+        true => vec![matching_groups
+            .iter()
+            .flat_map(|g| g.participants.clone())
+            .collect()],
+        false => matching_groups
+            .iter()
+            .map(|g| g.participants.clone())
+            .collect::<Vec<_>>(),
+    };
+
+    participant_sets
+        .iter()
+        .map(|participants| {
+            let weights: Vec<Vec<Option<i64>>> = participants
+                .iter()
+                .map(|giver| {
+                    participants
+                        .iter()
+                        .map(|receiver| {
+                            (giver.id != receiver.id
+                                && constraints.is_allowed(giver.id, receiver.id))
+                            .then_some(0)
+                        })
+                        .collect()
+                })
+                .collect();
+
+            solve_assignment(&weights)
+                .iter()
+                .filter(|receiver| receiver.is_some())
+                .count()
+        })
+        .sum()
+}
@@ -0,0 +1,152 @@
+use crate::structs::calendar::HolidayCalendar;
+use crate::structs::matching_round::{GroupFeedbackDate, MatchingRound};
+use crate::structs::participants_file::ParticipantsFile;
+
+use time::{Date, Duration, Weekday};
+
+pub const DEFAULT_FEEDBACK_WEEKDAY: Weekday = Weekday::Thursday;
+
+/// The feedback date of every group, on the weekday of the group in the week the interval ends
+pub fn get_feedback_dates(
+    match_date: Date,
+    intervall_weeks: i32,
+    participants_file: &ParticipantsFile,
+    calendar: &HolidayCalendar,
+) -> Vec<GroupFeedbackDate> {
+    participants_file
+        .groups
+        .iter()
+        .map(|group| GroupFeedbackDate {
+            group_id: group.id,
+            date: calculate_feedback_date(
+                match_date,
+                intervall_weeks,
+                group.feedback_weekday.unwrap_or(DEFAULT_FEEDBACK_WEEKDAY),
+                calendar,
+            ),
+        })
+        .collect()
+}
+
+/// The stored feedback dates of the round, recalculated if the round was created with a
+/// different interval
+pub fn get_feedback_dates_for_intervall(
+    matching_round: &MatchingRound,
+    intervall_weeks: i32,
+    participants_file: &ParticipantsFile,
+    calendar: &HolidayCalendar,
+) -> Vec<GroupFeedbackDate> {
+    match matching_round.intervall_weeks {
+        Some(round_intervall_weeks) if round_intervall_weeks != intervall_weeks => {
+            get_feedback_dates(
+                matching_round.date,
+                intervall_weeks,
+                participants_file,
+                calendar,
+            )
+        }
+        _ => matching_round.feedback_dates.clone(),
+    }
+}
+
+/// The feedback weekday in the week `intervall_weeks` after the match, moved to the next free
+/// working day if it's blocked in the calendar
+pub fn calculate_feedback_date(
+    match_date: Date,
+    intervall_weeks: i32,
+    feedback_weekday: Weekday,
+    calendar: &HolidayCalendar,
+) -> Date {
+    let feedback_weekday = feedback_weekday.number_days_from_sunday() as i32;
+    let match_weekday = match_date.weekday().number_days_from_sunday() as i32;
+    let days_to_add = (intervall_weeks * 7 + (feedback_weekday - match_weekday)) as i64;
+
+    calendar.next_free_day(match_date + Duration::days(days_to_add))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+    use crate::structs::calendar::BlockedDays;
+    use crate::structs::participants_file::ParticipantsGroup;
+    use crate::test_fixtures::matching_round;
+
+    #[test]
+    fn test_feedback_date_on_configured_weekday() {
+        let calendar = HolidayCalendar::default();
+
+        assert_eq!(
+            calculate_feedback_date(date!(2024 - 02 - 29), 2, Weekday::Tuesday, &calendar),
+            date!(2024 - 03 - 12)
+        );
+    }
+
+    #[test]
+    fn test_blocked_feedback_date_is_moved_to_next_working_day() {
+        let calendar = HolidayCalendar {
+            blocked_days: vec![
+                BlockedDays {
+                    date: date!(2024 - 03 - 14),
+                    until: Some(date!(2024 - 03 - 15)),
+                    description: Some("Offsite".to_string()),
+                },
+                BlockedDays {
+                    date: date!(2024 - 03 - 18),
+                    until: None,
+                    description: None,
+                },
+            ],
+        };
+
+        assert_eq!(
+            calculate_feedback_date(
+                date!(2024 - 02 - 29),
+                2,
+                DEFAULT_FEEDBACK_WEEKDAY,
+                &calendar
+            ),
+            date!(2024 - 03 - 19)
+        );
+    }
+
+    #[test]
+    fn test_feedback_dates_are_recalculated_for_a_different_intervall() {
+        let participants_file = ParticipantsFile {
+            groups: vec![ParticipantsGroup {
+                feedback_weekday: Some(Weekday::Tuesday),
+                ..ParticipantsGroup::new(1)
+            }],
+            constraints: Default::default(),
+        };
+        let calendar = HolidayCalendar {
+            blocked_days: vec![BlockedDays {
+                date: date!(2024 - 03 - 19),
+                until: None,
+                description: None,
+            }],
+        };
+        let stored_dates = vec![GroupFeedbackDate {
+            group_id: 1,
+            date: date!(2024 - 03 - 12),
+        }];
+        let matching_round = MatchingRound {
+            intervall_weeks: Some(2),
+            feedback_dates: stored_dates.clone(),
+            ..matching_round(1, date!(2024 - 02 - 29), vec![])
+        };
+
+        assert_eq!(
+            get_feedback_dates_for_intervall(&matching_round, 2, &participants_file, &calendar),
+            stored_dates
+        );
+        assert_eq!(
+            get_feedback_dates_for_intervall(&matching_round, 3, &participants_file, &calendar),
+            vec![GroupFeedbackDate {
+                group_id: 1,
+                date: date!(2024 - 03 - 20),
+            }]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::structs::participant::MatchParticipant;
+use crate::structs::r#match::Match;
+use crate::RECIPROCAL_PENALTY;
+
+use super::matcher::{
+    create_match, Matcher, MatchingContext, MatchingGroup, MatchingResult, ReciprocalMatches,
+};
+use super::objective::rate_scores;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use std::collections::HashSet;
+
+/// Greedily matches every giver with the best receiver left in a random order and keeps the
+/// best result of several tries
+pub struct GreedyMatcher {
+    pub number_of_tries: i32,
+}
+
+impl Matcher for GreedyMatcher {
+    fn find_matches(
+        &self,
+        matching_groups: &[MatchingGroup],
+        context: &MatchingContext,
+        rng: &mut dyn RngCore,
+    ) -> MatchingResult {
+        let mut best_rating = None;
+        let mut best_result = None;
+        let number_of_participants: usize =
+            matching_groups.iter().map(|g| g.participants.len()).sum();
+
+        for _ in 0..self.number_of_tries {
+            let mut result = MatchingResult::default();
+            let mut matched_pairs = HashSet::new();
+
+            for group in matching_groups {
+                let mut group_result = get_good_matches(
+                    &group.participants,
+                    &group.participants,
+                    context,
+                    &mut matched_pairs,
+                    context.cross_team_round,
+                    rng,
+                );
+
+                result.append(&mut group_result);
+            }
+
+            if context.cross_team_round {
+                let cross_team_result = get_good_matches(
+                    &result.unmatched_givers,
+                    &result.unmatched_receivers,
+                    context,
+                    &mut matched_pairs,
+                    false,
+                    rng,
+                );
+
+                result.matches.extend(cross_team_result.matches);
+                result.unmatched_givers = cross_team_result.unmatched_givers;
+                result.unmatched_receivers = cross_team_result.unmatched_receivers;
+            }
+
+            let rating = rate_matches(&result.matches, number_of_participants, context);
+
+            let is_better = match &best_rating {
+                Some(best) => *best < rating,
+                None => true,
+            };
+
+            if is_better {
+                best_rating = Some(rating);
+                best_result = Some(result);
+            }
+        }
+
+        best_result.unwrap_or_default()
+    }
+
+    fn number_of_tries(&self) -> Option<i32> {
+        Some(self.number_of_tries)
+    }
+}
+
+fn get_good_matches(
+    givers: &[MatchParticipant],
+    receivers: &[MatchParticipant],
+    context: &MatchingContext,
+    matched_pairs: &mut HashSet<(u32, u32)>,
+    skip_past_matches: bool,
+    rng: &mut dyn RngCore,
+) -> MatchingResult {
+    let mut unmatched_givers = get_shuffled_vector(givers, rng);
+    let mut unmatched_receivers = get_shuffled_vector(receivers, rng);
+
+    let mut matches: Vec<Match> = Vec::new();
+    let mut skipped_giver_count = 0;
+
+    while unmatched_givers.len() > skipped_giver_count {
+        let unmatched_giver = &unmatched_givers[0];
+
+        let best_receiver_index_and_score_option = get_optimal_receiver_index_and_score(
+            context,
+            matched_pairs,
+            unmatched_giver,
+            &unmatched_receivers,
+        );
+
+        match best_receiver_index_and_score_option {
+            Some((index, score)) => {
+                let receiver_id = unmatched_receivers[index].id;
+
+                if skip_past_matches && context.scorer.has_matched(unmatched_giver.id, receiver_id)
+                {
+                    skipped_giver_count += 1;
+                    continue;
+                }
+                let giver = unmatched_givers.swap_remove(0);
+                let receiver = unmatched_receivers.swap_remove(index);
+                matched_pairs.insert((giver.id, receiver.id));
+                matches.push(create_match(giver, receiver, score));
+            }
+            None => {
+                skipped_giver_count += 1;
+            }
+        }
+    }
+
+    MatchingResult {
+        matches,
+        unmatched_givers,
+        unmatched_receivers,
+    }
+}
+
+fn get_shuffled_vector<T: Clone>(vec: &[T], rng: &mut (impl Rng + ?Sized)) -> Vec<T> {
+    let mut cloned_vec = vec.to_vec();
+    cloned_vec.shuffle(rng);
+    cloned_vec
+}
+
+/// Finds the receiver with the highest score for the giver. Reciprocal matches are skipped or
+/// ranked below all other receivers, depending on the context.
+fn get_optimal_receiver_index_and_score(
+    context: &MatchingContext,
+    matched_pairs: &HashSet<(u32, u32)>,
+    giver: &MatchParticipant,
+    unmatched_receivers: &[MatchParticipant],
+) -> Option<(usize, i64)> {
+    let giver_id = giver.id;
+    let mut best_receiver_score = i64::MIN;
+    let mut best_receiver_index_and_score = None;
+
+    for (i, receiver) in unmatched_receivers.iter().enumerate() {
+        if giver_id == receiver.id || !context.constraints.is_allowed(giver_id, receiver.id) {
+            continue;
+        }
+
+        let score = context.scorer.score(giver, receiver);
+        let is_reciprocal = matched_pairs.contains(&(receiver.id, giver_id));
+
+        let ranking_score = match (context.reciprocal_matches, is_reciprocal) {
+            (ReciprocalMatches::Forbid, true) => continue,
+            (ReciprocalMatches::Penalize, true) => score - RECIPROCAL_PENALTY,
+            _ => score,
+        };
+
+        if best_receiver_score < ranking_score {
+            best_receiver_score = ranking_score;
+            best_receiver_index_and_score = Some((i, score));
+        }
+    }
+
+    best_receiver_index_and_score
+}
+
+/// Rates the matches with the objective of the context, penalizing reciprocal matches if needed
+fn rate_matches(
+    matches: &[Match],
+    number_of_participants: usize,
+    context: &MatchingContext,
+) -> Vec<i64> {
+    let pairs: HashSet<(u32, u32)> = matches
+        .iter()
+        .map(|m| (m.giver.id, m.receiver.id))
+        .collect();
+
+    let scores: Vec<i64> = matches
+        .iter()
+        .map(|m| {
+            let is_reciprocal = pairs.contains(&(m.receiver.id, m.giver.id));
+
+            match context.reciprocal_matches == ReciprocalMatches::Penalize && is_reciprocal {
+                true => m.score - RECIPROCAL_PENALTY,
+                false => m.score,
+            }
+        })
+        .collect();
+
+    rate_scores(
+        context.objective,
+        context.score_floor,
+        &scores,
+        number_of_participants,
+    )
+}
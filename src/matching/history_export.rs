@@ -0,0 +1,264 @@
+use super::last_match_map::{get_last_match_date_map, get_match_count_map};
+use crate::error::Error;
+use crate::file_io::write_atomically;
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participants_file::ParticipantsFile;
+
+use std::collections::BTreeMap;
+use std::fs;
+
+const HISTORY_COLUMNS: [&str; 11] = [
+    "round_id",
+    "date",
+    "giver_id",
+    "giver_first_name",
+    "giver_last_name",
+    "giver_group",
+    "receiver_id",
+    "receiver_first_name",
+    "receiver_last_name",
+    "receiver_group",
+    "score",
+];
+
+/// What the cells of the giver×receiver matrix show
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatrixValue {
+    /// The date of the last round the giver gave feedback to the receiver
+    #[default]
+    LastDate,
+    /// How often the giver gave feedback to the receiver
+    Count,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatrixFormat {
+    #[default]
+    Csv,
+    Markdown,
+}
+
+/// Writes one row per match of every round, oldest round first
+pub fn export_history(
+    file_path: &str,
+    past_matching_rounds: &[MatchingRound],
+) -> Result<(), Error> {
+    write_atomically(file_path, &to_csv(&create_history(past_matching_rounds)))
+}
+
+/// The header row followed by one row per match
+fn create_history(past_matching_rounds: &[MatchingRound]) -> Vec<Vec<String>> {
+    let mut rows = vec![HISTORY_COLUMNS.map(|column| column.to_string()).to_vec()];
+
+    for matching_round in past_matching_rounds {
+        rows.extend(matching_round.matches.iter().map(|past_match| {
+            let giver = &past_match.giver;
+            let receiver = &past_match.receiver;
+
+            vec![
+                matching_round.id.to_string(),
+                matching_round.date.to_string(),
+                giver.id.to_string(),
+                giver.first_name.clone(),
+                giver.last_name.clone(),
+                giver.group_id.to_string(),
+                receiver.id.to_string(),
+                receiver.first_name.clone(),
+                receiver.last_name.clone(),
+                receiver.group_id.to_string(),
+                past_match.score.to_string(),
+            ]
+        }));
+    }
+
+    rows
+}
+
+/// Writes a giver×receiver matrix of the pairs within each group into the directory and returns
+/// the paths of the written files. The current participants of a group are listed even if they
+/// have never been matched, so the gaps in the coverage show up as empty cells.
+pub fn export_matrices(
+    participants_file: &ParticipantsFile,
+    past_matching_rounds: &[MatchingRound],
+    value: MatrixValue,
+    format: MatrixFormat,
+    directory: &str,
+) -> Result<Vec<String>, Error> {
+    fs::create_dir_all(directory).map_err(|e| Error::io(directory, e))?;
+
+    let cells: BTreeMap<(u32, u32), String> = match value {
+        MatrixValue::LastDate => get_last_match_date_map(past_matching_rounds)
+            .into_iter()
+            .map(|(pair, date)| (pair, date.to_string()))
+            .collect(),
+        MatrixValue::Count => get_match_count_map(past_matching_rounds)
+            .into_iter()
+            .map(|(pair, count)| (pair, count.to_string()))
+            .collect(),
+    };
+
+    get_group_members(participants_file, past_matching_rounds)
+        .into_iter()
+        .map(|(group_id, members)| {
+            let rows = create_matrix(&members, &cells);
+            let (extension, contents) = match format {
+                MatrixFormat::Csv => ("csv", to_csv(&rows)),
+                MatrixFormat::Markdown => ("md", to_markdown(group_id, &rows)),
+            };
+
+            let file_path = format!("{directory}/matrix_group_{group_id}.{extension}");
+            write_atomically(&file_path, &contents)?;
+
+            Ok(file_path)
+        })
+        .collect()
+}
+
+/// The current participants of every group and everyone who has been matched in the group, by id
+fn get_group_members(
+    participants_file: &ParticipantsFile,
+    past_matching_rounds: &[MatchingRound],
+) -> BTreeMap<i32, BTreeMap<u32, String>> {
+    let mut group_members: BTreeMap<i32, BTreeMap<u32, String>> = BTreeMap::new();
+
+    let past_participants = past_matching_rounds
+        .iter()
+        .flat_map(|r| r.matches.iter())
+        .flat_map(|m| [&m.giver, &m.receiver]);
+
+    for participant in past_participants {
+        group_members
+            .entry(participant.group_id)
+            .or_default()
+            .insert(participant.id, participant.full_name());
+    }
+
+    // The current names win over the ones of the history
+    for group in &participants_file.groups {
+        let members = group_members.entry(group.id).or_default();
+
+        for participant in &group.participants {
+            members.insert(participant.id, participant.full_name());
+        }
+    }
+
+    group_members
+}
+
+/// The header row followed by one row per giver, the first column names the giver
+fn create_matrix(
+    members: &BTreeMap<u32, String>,
+    cells: &BTreeMap<(u32, u32), String>,
+) -> Vec<Vec<String>> {
+    let label = |id: &u32, name: &String| format!("{id} {name}");
+
+    let mut header = vec!["giver \\ receiver".to_string()];
+    header.extend(members.iter().map(|(id, name)| label(id, name)));
+
+    let mut rows = vec![header];
+    rows.extend(members.iter().map(|(giver_id, giver_name)| {
+        let mut row = vec![label(giver_id, giver_name)];
+        row.extend(members.keys().map(|receiver_id| {
+            match giver_id == receiver_id {
+                true => "-".to_string(),
+                false => cells
+                    .get(&(*giver_id, *receiver_id))
+                    .cloned()
+                    .unwrap_or_default(),
+            }
+        }));
+        row
+    }));
+
+    rows
+}
+
+fn to_csv(rows: &[Vec<String>]) -> String {
+    let mut writer = csv::Writer::from_writer(vec![]);
+
+    for row in rows {
+        writer.write_record(row).expect("Failed to write to memory");
+    }
+
+    String::from_utf8_lossy(&writer.into_inner().expect("Failed to write to memory")).to_string()
+}
+
+fn to_markdown(group_id: i32, rows: &[Vec<String>]) -> String {
+    let to_line = |row: &Vec<String>| {
+        let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut lines = vec![format!("# Group {group_id}"), String::new()];
+    lines.push(to_line(&rows[0]));
+    lines.push(format!("|{}", "---|".repeat(rows[0].len())));
+    lines.extend(rows[1..].iter().map(to_line));
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::participant::MatchParticipant;
+    use crate::structs::r#match::Match;
+    use crate::test_fixtures::{match_participant, matching_round};
+    use time::macros::date;
+
+    #[test]
+    fn test_history() {
+        let anna = MatchParticipant {
+            first_name: "Anna".to_string(),
+            last_name: "Alt, Jr.".to_string(),
+            ..match_participant(1, 1)
+        };
+        let ben = MatchParticipant {
+            first_name: "Ben".to_string(),
+            last_name: "Alt, Jr.".to_string(),
+            ..match_participant(2, 2)
+        };
+        let pair_match = |giver: &MatchParticipant, receiver: &MatchParticipant, score| Match {
+            giver: giver.clone(),
+            receiver: receiver.clone(),
+            score,
+        };
+        let rounds = vec![
+            matching_round(1, date!(2024 - 03 - 07), vec![pair_match(&anna, &ben, 42)]),
+            matching_round(2, date!(2024 - 04 - 04), vec![]),
+            matching_round(
+                3,
+                date!(2024 - 05 - 02),
+                vec![pair_match(&ben, &anna, 7), pair_match(&anna, &ben, -3)],
+            ),
+        ];
+
+        assert_eq!(
+            to_csv(&create_history(&rounds)),
+            "round_id,date,giver_id,giver_first_name,giver_last_name,giver_group,\
+             receiver_id,receiver_first_name,receiver_last_name,receiver_group,score\n\
+             1,2024-03-07,1,Anna,\"Alt, Jr.\",1,2,Ben,\"Alt, Jr.\",2,42\n\
+             3,2024-05-02,2,Ben,\"Alt, Jr.\",2,1,Anna,\"Alt, Jr.\",1,7\n\
+             3,2024-05-02,1,Anna,\"Alt, Jr.\",1,2,Ben,\"Alt, Jr.\",2,-3\n"
+        );
+    }
+
+    #[test]
+    fn test_matrix() {
+        let members = BTreeMap::from([(1, "Anna A".to_string()), (2, "Ben B".to_string())]);
+        let cells = BTreeMap::from([((1, 2), "2".to_string()), ((2, 3), "1".to_string())]);
+        let rows = create_matrix(&members, &cells);
+
+        assert_eq!(
+            to_markdown(1, &rows),
+            "# Group 1\n\n\
+             | giver \\ receiver | 1 Anna A | 2 Ben B |\n\
+             |---|---|---|\n\
+             | 1 Anna A | - | 2 |\n\
+             | 2 Ben B |  | - |\n"
+        );
+        assert_eq!(
+            to_csv(&rows),
+            "giver \\ receiver,1 Anna A,2 Ben B\n1 Anna A,-,2\n2 Ben B,,-\n"
+        );
+    }
+}
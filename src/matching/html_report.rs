@@ -0,0 +1,440 @@
+use super::complete_givers::get_complete_givers;
+use super::last_match_map::get_last_match_date_map;
+use super::matcher::count_reciprocal_matches;
+use super::round_summary::{get_score_counts, get_score_range, get_settings_info, get_switch_info};
+use super::scoring::{Scorer, ScoringConfig};
+use super::stats::get_stats;
+use crate::error::Error;
+use crate::file_io::write_atomically;
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participant::map_participant_to_match_participant;
+use crate::structs::participants_file::ParticipantsFile;
+
+use time::Date;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; }
+td.cell { text-align: center; min-width: 2.5em; }
+td.self { background: #eee; }
+.bar { background: #4a7; height: 1em; }
+.complete { color: #282; }
+.incomplete { color: #a33; }
+";
+
+/// Writes a single HTML file without external assets with the rounds, the coverage of every
+/// group with the current pair scores, the score trend across the rounds, the score
+/// distribution of every round and the complete givers
+pub fn write_html_report(
+    file_path: &str,
+    participants_file: &ParticipantsFile,
+    past_matching_rounds: &[MatchingRound],
+    scoring_config: &ScoringConfig,
+    as_of: Date,
+) -> Result<(), Error> {
+    let html = create_html_report(
+        participants_file,
+        past_matching_rounds,
+        scoring_config,
+        as_of,
+    );
+
+    write_atomically(file_path, &html)
+}
+
+fn create_html_report(
+    participants_file: &ParticipantsFile,
+    past_matching_rounds: &[MatchingRound],
+    scoring_config: &ScoringConfig,
+    as_of: Date,
+) -> String {
+    let mut body = vec![
+        "<h1>Feedback matching report</h1>".to_string(),
+        format!(
+            "<p>As of {as_of}, {} rounds</p>",
+            past_matching_rounds.len()
+        ),
+        "<h2>Coverage</h2>".to_string(),
+    ];
+    body.extend(create_coverage_sections(
+        participants_file,
+        past_matching_rounds,
+        &Scorer::new(scoring_config, past_matching_rounds, as_of),
+    ));
+
+    body.push("<h2>Complete givers</h2>".to_string());
+    body.push(create_complete_givers_table(
+        participants_file,
+        past_matching_rounds,
+    ));
+
+    body.push("<h2>Score trend</h2>".to_string());
+    body.push(create_score_trend_table(past_matching_rounds));
+
+    body.push("<h2>Rounds</h2>".to_string());
+    body.extend(past_matching_rounds.iter().rev().map(create_round_section));
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Feedback matching report</title>\n<style>{STYLE}</style>\n</head>\n\
+         <body>\n{}\n</body>\n</html>\n",
+        body.join("\n")
+    )
+}
+
+/// A giver×receiver heatmap of the active participants of every group, darker cells have been
+/// matched more often. The tooltips show how long ago the pair was matched and its score for a
+/// round on the as-of date of the scorer.
+fn create_coverage_sections(
+    participants_file: &ParticipantsFile,
+    past_matching_rounds: &[MatchingRound],
+    scorer: &Scorer,
+) -> Vec<String> {
+    let stats = get_stats(participants_file, past_matching_rounds);
+    let match_count_map = &scorer.match_count_map;
+    let last_match_date_map = get_last_match_date_map(past_matching_rounds);
+    let max_count = match_count_map.values().copied().max().unwrap_or(0);
+
+    participants_file
+        .groups
+        .iter()
+        .zip(&stats.groups)
+        .map(|(group, group_stats)| {
+            let participants = &group.participants;
+            let mut lines = vec![
+                format!("<h3>Group {}</h3>", group.id),
+                format!(
+                    "<p>{:.1} % of the pairs covered ({} of {})</p>",
+                    group_stats.coverage_percent,
+                    group_stats.covered_pairs,
+                    group_stats.possible_pairs
+                ),
+                "<table>".to_string(),
+            ];
+
+            let header: String = participants
+                .iter()
+                .map(|p| format!("<th>{}</th>", escape_html(&p.full_name())))
+                .collect();
+            lines.push(format!("<tr><th>Giver \\ Receiver</th>{header}</tr>"));
+
+            for giver in participants {
+                let cells: String = participants
+                    .iter()
+                    .map(|receiver| {
+                        if giver.id == receiver.id {
+                            return "<td class=\"cell self\"></td>".to_string();
+                        }
+
+                        let pair = (giver.id, receiver.id);
+                        let count = match_count_map.get(&pair).copied().unwrap_or(0);
+                        let last_match = last_match_date_map
+                            .get(&pair)
+                            .zip(scorer.last_match_map.get(&pair))
+                            .map(|(date, days)| (*date, *days));
+                        let score = scorer.score(
+                            &map_participant_to_match_participant(giver, group.id, group.locale),
+                            &map_participant_to_match_participant(receiver, group.id, group.locale),
+                        );
+
+                        create_heatmap_cell(count, max_count, last_match, score)
+                    })
+                    .collect();
+
+                lines.push(format!(
+                    "<tr><th>{}</th>{cells}</tr>",
+                    escape_html(&giver.full_name())
+                ));
+            }
+
+            lines.push("</table>".to_string());
+            lines.join("\n")
+        })
+        .collect()
+}
+
+/// The number of matches, with the date and age in days of the last one and the current score
+/// as tooltip
+fn create_heatmap_cell(
+    count: u32,
+    max_count: u32,
+    last_match: Option<(Date, i64)>,
+    score: i64,
+) -> String {
+    let title = match last_match {
+        Some((date, days)) => format!("{count}x, last on {date}, {days} days ago, score {score}"),
+        None => format!("Never matched, score {score}"),
+    };
+    let text = match count {
+        0 => String::new(),
+        _ => count.to_string(),
+    };
+
+    format!(
+        "<td class=\"cell\" style=\"background: {}\" title=\"{title}\">{text}</td>",
+        get_heat_color(count, max_count)
+    )
+}
+
+/// White for pairs that have never been matched, then from light to dark green
+fn get_heat_color(count: u32, max_count: u32) -> String {
+    if count == 0 || max_count == 0 {
+        return "#fff".to_string();
+    }
+
+    let lightness = 85 - (count * 45 / max_count);
+    format!("hsl(140, 45%, {lightness}%)")
+}
+
+fn create_complete_givers_table(
+    participants_file: &ParticipantsFile,
+    past_matching_rounds: &[MatchingRound],
+) -> String {
+    let complete_givers_by_group = get_complete_givers(participants_file, past_matching_rounds);
+    let mut lines = vec![
+        "<table>".to_string(),
+        "<tr><th>Group</th><th>Participant</th><th>Status</th></tr>".to_string(),
+    ];
+
+    for group in &participants_file.groups {
+        let complete_givers = &complete_givers_by_group[&group.id];
+
+        for participant in &group.participants {
+            let status = match complete_givers.iter().any(|g| g.id == participant.id) {
+                true => "<span class=\"complete\">Matched everyone</span>",
+                false => "<span class=\"incomplete\">Open receivers</span>",
+            };
+
+            lines.push(format!(
+                "<tr><td>{}</td><td>{}</td><td>{status}</td></tr>",
+                group.id,
+                escape_html(&participant.full_name())
+            ));
+        }
+    }
+
+    lines.push("</table>".to_string());
+    lines.join("\n")
+}
+
+/// The lowest, average and highest score of every round, oldest round first
+fn create_score_trend_table(past_matching_rounds: &[MatchingRound]) -> String {
+    let mut lines = vec![
+        "<table>".to_string(),
+        "<tr><th>Round</th><th>Date</th><th>Lowest</th><th>Average</th><th>Highest</th></tr>"
+            .to_string(),
+    ];
+
+    for matching_round in past_matching_rounds {
+        let Some(score_range) = get_score_range(&matching_round.matches) else {
+            continue;
+        };
+
+        lines.push(format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            matching_round.id,
+            matching_round.date,
+            score_range.min,
+            score_range.mean,
+            score_range.max
+        ));
+    }
+
+    lines.push("</table>".to_string());
+    lines.join("\n")
+}
+
+/// The matches of the round like the result of a new match, followed by a bar chart of the
+/// scores
+fn create_round_section(matching_round: &MatchingRound) -> String {
+    let mut lines = vec![format!(
+        "<h3>Round {} on {}</h3>",
+        matching_round.id, matching_round.date
+    )];
+
+    if let Some(settings_info) = get_settings_info(matching_round) {
+        lines.push(format!("<p>{settings_info}</p>"));
+    }
+
+    lines.push("<table>".to_string());
+    lines.push("<tr><th>Match</th><th>Giver</th><th>Receiver</th><th>Score</th></tr>".to_string());
+
+    for group_match in &matching_round.matches {
+        lines.push(format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            get_switch_info(group_match),
+            escape_html(&group_match.giver.full_name()),
+            escape_html(&group_match.receiver.full_name()),
+            group_match.score
+        ));
+    }
+
+    lines.push("</table>".to_string());
+
+    let score_counts = get_score_counts(&matching_round.matches);
+    let max_count = score_counts.values().copied().max().unwrap_or(1);
+
+    if !score_counts.is_empty() {
+        lines.push("<table>".to_string());
+        lines.push("<tr><th>Score</th><th>Matches</th><th></th></tr>".to_string());
+
+        for (score, count) in &score_counts {
+            lines.push(format!(
+                "<tr><td>{score}</td><td>{count}</td>\
+                 <td style=\"width: 12em\"><div class=\"bar\" style=\"width: {}%\"></div></td></tr>",
+                count * 100 / max_count
+            ));
+        }
+
+        lines.push("</table>".to_string());
+    }
+
+    lines.push(format!(
+        "<p>Reciprocal matches: {}</p>",
+        count_reciprocal_matches(&matching_round.matches)
+    ));
+
+    lines.join("\n")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::scoring::RecencyCurve;
+    use crate::structs::participant::Participant;
+    use crate::structs::participants_file::{Constraints, PairConstraint, ParticipantsGroup};
+    use crate::structs::r#match::Match;
+    use crate::test_fixtures;
+    use time::macros::date;
+
+    fn participant(id: u32, first_name: &str) -> Participant {
+        Participant {
+            first_name: first_name.to_string(),
+            last_name: "<B>".to_string(),
+            ..test_fixtures::participant(id)
+        }
+    }
+
+    fn matching_round(
+        id: i32,
+        date: Date,
+        pairs: &[(&Participant, &Participant, i64)],
+    ) -> MatchingRound {
+        let matches = pairs
+            .iter()
+            .map(|(giver, receiver, score)| Match {
+                giver: map_participant_to_match_participant(giver, 1, None),
+                receiver: map_participant_to_match_participant(receiver, 1, None),
+                score: *score,
+            })
+            .collect();
+
+        test_fixtures::matching_round(id, date, matches)
+    }
+
+    #[test]
+    fn test_report() {
+        let anna = participant(1, "Anna");
+        let ben = participant(2, "Ben");
+        let cara = participant(3, "Cara");
+        let participants_file = ParticipantsFile {
+            groups: vec![ParticipantsGroup {
+                participants: vec![anna.clone(), ben.clone(), cara.clone()],
+                ..ParticipantsGroup::new(1)
+            }],
+            constraints: Constraints {
+                forbidden_pairs: vec![PairConstraint {
+                    giver_id: 2,
+                    receiver_id: 3,
+                }],
+                required_pairs: vec![],
+            },
+        };
+        let scoring_config = ScoringConfig {
+            never_matched_score: 100,
+            recency_curve: RecencyCurve::Linear { factor: 1.0 },
+            cross_team_bonus: 0,
+            same_gender_score: 0,
+            different_gender_score: 0,
+            past_match_penalty: 10,
+        };
+        let rounds = vec![
+            matching_round(
+                1,
+                date!(2024 - 03 - 07),
+                &[(&anna, &ben, 42), (&anna, &cara, 8)],
+            ),
+            matching_round(2, date!(2024 - 03 - 21), &[(&ben, &anna, 30)]),
+        ];
+
+        let html = create_html_report(
+            &participants_file,
+            &rounds,
+            &scoring_config,
+            date!(2024 - 03 - 31),
+        );
+
+        assert!(html.contains("<p>As of 2024-03-31, 2 rounds</p>"));
+        // The forbidden pair isn't possible, Ben has matched everyone else
+        assert!(html.contains(
+            "<h3>Group 1</h3>\n\
+             <p>60.0 % of the pairs covered (3 of 5)</p>"
+        ));
+        assert!(html.contains(
+            "<tr><th>Anna &lt;B&gt;</th><td class=\"cell self\"></td>\
+             <td class=\"cell\" style=\"background: hsl(140, 45%, 40%)\" \
+             title=\"1x, last on 2024-03-07, 24 days ago, score 14\">1</td>"
+        ));
+        assert!(html.contains(
+            "<tr><th>Cara &lt;B&gt;</th><td class=\"cell\" style=\"background: #fff\" \
+             title=\"Never matched, score 100\"></td>"
+        ));
+        assert!(html.contains(
+            "<h2>Complete givers</h2>\n\
+             <table>\n\
+             <tr><th>Group</th><th>Participant</th><th>Status</th></tr>\n\
+             <tr><td>1</td><td>Anna &lt;B&gt;</td>\
+             <td><span class=\"complete\">Matched everyone</span></td></tr>\n\
+             <tr><td>1</td><td>Ben &lt;B&gt;</td>\
+             <td><span class=\"complete\">Matched everyone</span></td></tr>\n\
+             <tr><td>1</td><td>Cara &lt;B&gt;</td>\
+             <td><span class=\"incomplete\">Open receivers</span></td></tr>\n\
+             </table>"
+        ));
+        assert!(html.contains(
+            "<h2>Score trend</h2>\n\
+             <table>\n\
+             <tr><th>Round</th><th>Date</th><th>Lowest</th><th>Average</th><th>Highest</th></tr>\n\
+             <tr><td>1</td><td>2024-03-07</td><td>8</td><td>25</td><td>42</td></tr>\n\
+             <tr><td>2</td><td>2024-03-21</td><td>30</td><td>30</td><td>30</td></tr>\n\
+             </table>"
+        ));
+        assert!(html.contains(
+            "<tr><td>In-Team Match in group 1</td><td>Anna &lt;B&gt;</td><td>Ben &lt;B&gt;</td>\
+             <td>42</td></tr>"
+        ));
+    }
+
+    #[test]
+    fn test_heat_color() {
+        assert_eq!(get_heat_color(0, 3), "#fff");
+        assert_eq!(get_heat_color(1, 3), "hsl(140, 45%, 70%)");
+        assert_eq!(get_heat_color(3, 3), "hsl(140, 45%, 40%)");
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<Anna & \"Ben\">"),
+            "&lt;Anna &amp; &quot;Ben&quot;&gt;"
+        );
+    }
+}
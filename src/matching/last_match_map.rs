@@ -1,13 +1,17 @@
-use crate::{structs::matching_round::MatchingRound, MAX_SCORE};
+use crate::structs::matching_round::MatchingRound;
 
 use std::collections::HashMap;
-use time::OffsetDateTime;
+use time::Date;
 
-pub fn get_last_match_map(past_matching_rounds: &Vec<MatchingRound>) -> HashMap<(u32, u32), i64> {
+/// The days from the last match of every pair until the as-of date
+pub fn get_last_match_map(
+    past_matching_rounds: &[MatchingRound],
+    as_of: Date,
+) -> HashMap<(u32, u32), i64> {
     let mut last_match_map: HashMap<(u32, u32), i64> = HashMap::new();
 
     for matching_round in past_matching_rounds {
-        let days_since_matching_round = get_days_since_matching_round(matching_round);
+        let days_since_matching_round = get_days_since_matching_round(matching_round, as_of);
 
         for past_match in &matching_round.matches {
             last_match_map.insert(
@@ -17,26 +21,57 @@ pub fn get_last_match_map(past_matching_rounds: &Vec<MatchingRound>) -> HashMap<
         }
     }
 
-    return last_match_map;
+    last_match_map
 }
 
-fn get_days_since_matching_round(matching_round: &MatchingRound) -> i64 {
-    let today = OffsetDateTime::now_utc().date();
-    let time_since_last_match = today - matching_round.date;
-    return time_since_last_match.whole_days();
+/// The date of the last matching round of every pair
+pub fn get_last_match_date_map(
+    past_matching_rounds: &[MatchingRound],
+) -> HashMap<(u32, u32), Date> {
+    let mut last_match_date_map: HashMap<(u32, u32), Date> = HashMap::new();
+
+    for matching_round in past_matching_rounds {
+        for past_match in &matching_round.matches {
+            last_match_date_map
+                .entry((past_match.giver.id, past_match.receiver.id))
+                .and_modify(|date| *date = (*date).max(matching_round.date))
+                .or_insert(matching_round.date);
+        }
+    }
+
+    last_match_date_map
 }
 
-pub fn get_days_since_last_match(
-    last_match_map: &HashMap<(u32, u32), i64>,
-    giver_id: u32,
-    receiver_id: u32,
-) -> i64 {
-    let days_since_last_match_option = last_match_map.get(&(giver_id, receiver_id));
+pub fn get_match_count_map(past_matching_rounds: &[MatchingRound]) -> HashMap<(u32, u32), u32> {
+    let mut match_count_map: HashMap<(u32, u32), u32> = HashMap::new();
 
-    let days_since_last_match = match &days_since_last_match_option {
-        Some(duration) => **duration,
-        None => MAX_SCORE,
-    };
+    for matching_round in past_matching_rounds {
+        for past_match in &matching_round.matches {
+            *match_count_map
+                .entry((past_match.giver.id, past_match.receiver.id))
+                .or_insert(0) += 1;
+        }
+    }
+
+    match_count_map
+}
+
+/// Treats every past match as if it also happened in the reverse direction, so a pair that
+/// just swapped roles counts as recently matched
+pub fn add_reverse_matches(last_match_map: &HashMap<(u32, u32), i64>) -> HashMap<(u32, u32), i64> {
+    let mut symmetric_last_match_map = last_match_map.clone();
+
+    for ((giver_id, receiver_id), days) in last_match_map {
+        symmetric_last_match_map
+            .entry((*receiver_id, *giver_id))
+            .and_modify(|reverse_days| *reverse_days = (*reverse_days).min(*days))
+            .or_insert(*days);
+    }
+
+    symmetric_last_match_map
+}
 
-    days_since_last_match
+fn get_days_since_matching_round(matching_round: &MatchingRound, as_of: Date) -> i64 {
+    let time_since_last_match = as_of - matching_round.date;
+    time_since_last_match.whole_days()
 }
@@ -0,0 +1,115 @@
+use crate::structs::participant::MatchParticipant;
+use crate::structs::r#match::Match;
+
+use super::constraints::MatchingConstraints;
+use super::greedy::GreedyMatcher;
+use super::objective::Objective;
+use super::optimal::OptimalMatcher;
+use super::rotation::RotationMatcher;
+use super::scoring::Scorer;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub struct MatchingGroup {
+    pub participants: Vec<MatchParticipant>,
+}
+
+/// Everything besides the participants a matcher needs to know about the round
+pub struct MatchingContext<'a> {
+    pub scorer: &'a Scorer,
+    pub cross_team_round: bool,
+    pub matching_round_id: i32,
+    pub constraints: &'a MatchingConstraints,
+    pub reciprocal_matches: ReciprocalMatches,
+    pub objective: Objective,
+    /// The lowest score every match should reach for the mean with floor objective
+    pub score_floor: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MatchingResult {
+    pub matches: Vec<Match>,
+    pub unmatched_givers: Vec<MatchParticipant>,
+    pub unmatched_receivers: Vec<MatchParticipant>,
+}
+
+impl MatchingResult {
+    pub fn append(&mut self, other: &mut MatchingResult) {
+        self.matches.append(&mut other.matches);
+        self.unmatched_givers.append(&mut other.unmatched_givers);
+        self.unmatched_receivers
+            .append(&mut other.unmatched_receivers);
+    }
+}
+
+pub trait Matcher {
+    /// Matches the participants of all groups for one round. In cross-team rounds
+    /// participants may be matched with participants from other groups. Pairs that are not
+    /// allowed by the constraints must never be matched.
+    fn find_matches(
+        &self,
+        matching_groups: &[MatchingGroup],
+        context: &MatchingContext,
+        rng: &mut dyn RngCore,
+    ) -> MatchingResult;
+
+    /// The number of randomized tries, if the matcher makes several
+    fn number_of_tries(&self) -> Option<i32> {
+        None
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchingStrategy {
+    /// Keep the best of several randomized greedy matchings
+    Greedy,
+    /// Solve the assignment problem exactly for the best possible matching
+    Optimal,
+    /// Rotate the receivers by a fixed offset that changes every round
+    Rotation,
+}
+
+/// How to handle two participants giving feedback to each other in the same round
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReciprocalMatches {
+    /// Reciprocal matches are treated like any other match
+    #[default]
+    Allow,
+    /// Reciprocal matches are only used if everyone can't be matched otherwise
+    Penalize,
+    /// Reciprocal matches are never used, even if participants stay unmatched
+    Forbid,
+}
+
+impl MatchingStrategy {
+    pub fn matcher(&self, number_of_tries: i32) -> Box<dyn Matcher> {
+        match self {
+            MatchingStrategy::Greedy => Box::new(GreedyMatcher { number_of_tries }),
+            MatchingStrategy::Optimal => Box::new(OptimalMatcher {}),
+            MatchingStrategy::Rotation => Box::new(RotationMatcher {}),
+        }
+    }
+}
+
+pub fn create_match(giver: MatchParticipant, receiver: MatchParticipant, score: i64) -> Match {
+    Match {
+        giver,
+        receiver,
+        score,
+    }
+}
+
+/// The number of matches whose giver also gives feedback to their receiver
+pub fn count_reciprocal_matches(matches: &[Match]) -> usize {
+    let pairs: HashSet<(u32, u32)> = matches
+        .iter()
+        .map(|m| (m.giver.id, m.receiver.id))
+        .collect();
+
+    matches
+        .iter()
+        .filter(|m| pairs.contains(&(m.receiver.id, m.giver.id)))
+        .count()
+}
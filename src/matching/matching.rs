@@ -1,100 +1,79 @@
 use crate::structs::matching_round::MatchingRound;
-use crate::structs::participant::{map_participants_to_match_participants, MatchParticipant};
+use crate::structs::participant::map_participants_to_match_participants;
 use crate::structs::participants_file::ParticipantsFile;
-use crate::structs::r#match::Match;
-use crate::{MAX_SCORE, NUMBER_OF_TRIES};
 
-use super::last_match_map::{get_days_since_last_match, get_last_match_map};
-use rand::seq::SliceRandom;
-use rand::Rng;
-use std::collections::HashMap;
-use time::OffsetDateTime;
+use super::constraints::{get_max_number_of_matches, MatchingConstraints};
+use super::matcher::{MatchingContext, MatchingGroup, MatchingStrategy, ReciprocalMatches};
+use super::objective::Objective;
+use super::scoring::{Scorer, ScoringConfig};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use time::Date;
 
 #[derive(Debug, Clone)]
-struct MatchingGroup {
-    participants: Vec<MatchParticipant>,
+pub struct MatchingOptions {
+    pub cross_team_round: bool,
+    pub strategy: MatchingStrategy,
+    pub number_of_tries: i32,
+    pub seed: u64,
+    pub reciprocal_matches: ReciprocalMatches,
+    /// Count a past match as recent for both directions of the pair
+    pub reverse_matches_as_recent: bool,
+    pub objective: Objective,
+    pub score_floor: i64,
+    /// The date of the new round, the days since the past matches are counted until then
+    pub as_of: Date,
 }
 
+/// Creates the next matching round. The same options always produce the same matches for the
+/// same participants and history.
 pub fn match_participants(
     participants_file: &ParticipantsFile,
-    past_matching_rounds: &Vec<MatchingRound>,
-    cross_team_round: bool,
-    rng: &mut impl Rng,
+    past_matching_rounds: &[MatchingRound],
+    options: &MatchingOptions,
+    constraints: &MatchingConstraints,
+    scoring_config: &ScoringConfig,
 ) -> MatchingRound {
     let matching_groups: Vec<MatchingGroup> = participants_file
         .groups
         .iter()
         .map(|g| MatchingGroup {
-            participants: map_participants_to_match_participants(&g.participants, g.id),
+            participants: map_participants_to_match_participants(&g.participants, g.id, g.locale),
         })
         .collect();
 
-    let last_match_map = get_last_match_map(past_matching_rounds);
-    let mut best_score = i64::MIN;
-    let mut best_matches_and_score = None;
-    let number_of_participants: usize = participants_file
-        .groups
-        .iter()
-        .map(|g| g.participants.len())
-        .sum();
-
-    for _ in 0..NUMBER_OF_TRIES {
-        let mut overall_matches: Vec<Match> = Vec::new();
-        let mut overall_unmatched_givers = Vec::new();
-        let mut overall_unmatched_receivers = Vec::new();
-
-        for group in &matching_groups {
-            // why mut?
-            let (mut matches, mut unmatched_givers, mut unmatched_receivers) = get_good_matches(
-                &group.participants,
-                &group.participants,
-                &last_match_map,
-                cross_team_round,
-                rng,
-            );
+    let mut scorer = Scorer::new(scoring_config, past_matching_rounds, options.as_of);
 
-            overall_matches.append(&mut matches);
-            overall_unmatched_givers.append(&mut unmatched_givers);
-            overall_unmatched_receivers.append(&mut unmatched_receivers);
-        }
+    if options.reverse_matches_as_recent {
+        scorer = scorer.with_reverse_matches();
+    }
 
-        if cross_team_round {
-            let (mut matches, unmatched_givers, unmatched_receivers) = get_good_matches(
-                &overall_unmatched_givers,
-                &overall_unmatched_receivers,
-                &last_match_map,
-                false,
-                rng,
-            );
+    let next_matching_round_id = get_next_matching_round_id(past_matching_rounds);
 
-            overall_matches.append(&mut matches);
-            overall_unmatched_givers = unmatched_givers;
-            overall_unmatched_receivers = unmatched_receivers;
-        }
+    let context = MatchingContext {
+        scorer: &scorer,
+        cross_team_round: options.cross_team_round,
+        matching_round_id: next_matching_round_id,
+        constraints,
+        reciprocal_matches: options.reciprocal_matches,
+        objective: options.objective,
+        score_floor: options.score_floor,
+    };
 
-        let score = score_matches(&overall_matches, number_of_participants);
-        let unmatched_givers = overall_unmatched_givers
-            .iter()
-            .map(|g| g.full_name())
-            .collect::<Vec<String>>();
-        let unmatched_receivers = overall_unmatched_receivers
-            .iter()
-            .map(|r| r.full_name())
-            .collect::<Vec<String>>();
-
-        if best_score < score {
-            best_score = score;
-            best_matches_and_score = Some((
-                overall_matches,
-                score,
-                unmatched_givers,
-                unmatched_receivers,
-            ));
-        }
-    }
+    let matcher = options.strategy.matcher(options.number_of_tries);
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let result = matcher.find_matches(&matching_groups, &context, &mut rng);
 
-    let (matches, _score, unmatched_givers, unmatched_receivers) =
-        best_matches_and_score.expect("No matches created");
+    let unmatched_givers = result
+        .unmatched_givers
+        .iter()
+        .map(|g| g.full_name())
+        .collect::<Vec<String>>();
+    let unmatched_receivers = result
+        .unmatched_receivers
+        .iter()
+        .map(|r| r.full_name())
+        .collect::<Vec<String>>();
 
     if !unmatched_givers.is_empty() {
         println!("Unmatched givers: {:#?}", unmatched_givers);
@@ -104,112 +83,400 @@ pub fn match_participants(
         println!("Unmatched receivers: {:#?}", unmatched_receivers);
     }
 
-    let next_matching_round_id = get_next_matching_round_id(past_matching_rounds);
+    if !unmatched_givers.is_empty() && !constraints.is_empty() {
+        print_constraint_limits(&matching_groups, constraints, options.cross_team_round);
+    }
 
-    let matching_round = MatchingRound {
+    MatchingRound {
         id: next_matching_round_id,
-        date: OffsetDateTime::now_utc().date(),
-        matches,
-    };
+        date: options.as_of,
+        matches: result.matches,
+        seed: Some(options.seed),
+        strategy: Some(options.strategy),
+        number_of_tries: matcher.number_of_tries(),
+        intervall_weeks: None,
+        feedback_dates: vec![],
+    }
+}
 
-    matching_round
+fn print_constraint_limits(
+    matching_groups: &[MatchingGroup],
+    constraints: &MatchingConstraints,
+    cross_team_round: bool,
+) {
+    let max_number_of_matches =
+        get_max_number_of_matches(matching_groups, constraints, cross_team_round);
+    let max_number_of_unconstrained_matches = get_max_number_of_matches(
+        matching_groups,
+        &MatchingConstraints::default(),
+        cross_team_round,
+    );
+
+    if max_number_of_matches < max_number_of_unconstrained_matches {
+        println!(
+            "The constraints make a full matching impossible: at most {max_number_of_matches} of {max_number_of_unconstrained_matches} givers can be matched"
+        );
+    }
 }
 
-fn get_good_matches(
-    givers: &Vec<MatchParticipant>,
-    receivers: &Vec<MatchParticipant>,
-    last_match_map: &HashMap<(u32, u32), i64>,
-    skip_matches_below_max_score: bool,
-    rng: &mut impl Rng,
-) -> (Vec<Match>, Vec<MatchParticipant>, Vec<MatchParticipant>) {
-    let mut unmatched_givers = get_shuffled_vector(givers, rng);
-    let mut unmatched_receivers = get_shuffled_vector(receivers, rng);
-
-    let mut matches: Vec<Match> = Vec::new();
-    let mut skipped_giver_count = 0;
-
-    while unmatched_givers.len() > skipped_giver_count {
-        let unmatched_giver = &unmatched_givers[0];
-        let giver_id = unmatched_giver.id;
-
-        let best_receiver_index_and_score_option =
-            get_optimal_receiver_index_and_score(last_match_map, giver_id, &unmatched_receivers);
-
-        match best_receiver_index_and_score_option {
-            Some((index, score)) => {
-                if skip_matches_below_max_score && score < MAX_SCORE {
-                    skipped_giver_count += 1;
-                    continue;
-                }
-                let giver = unmatched_givers.swap_remove(0);
-                let receiver = unmatched_receivers.swap_remove(index);
-                matches.push(create_match(giver, receiver, score));
-            }
-            None => {
-                skipped_giver_count += 1;
-            }
+fn get_next_matching_round_id(past_matching_rounds: &[MatchingRound]) -> i32 {
+    let last_matching_round = past_matching_rounds.last();
+
+    match last_matching_round {
+        Some(matching_round) => matching_round.id + 1,
+        None => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::matcher::count_reciprocal_matches;
+    use crate::structs::participants_file::{Constraints, PairConstraint, ParticipantsGroup};
+    use crate::test_fixtures::participant;
+    use crate::NUMBER_OF_TRIES;
+    use time::macros::date;
+
+    fn participants_file(groups: Vec<Vec<u32>>) -> ParticipantsFile {
+        ParticipantsFile {
+            groups: groups
+                .into_iter()
+                .enumerate()
+                .map(|(i, ids)| ParticipantsGroup {
+                    id: i as i32 + 1,
+                    participants: ids.into_iter().map(participant).collect(),
+                    excluded_participants: vec![],
+                    locale: None,
+                    feedback_weekday: None,
+                })
+                .collect(),
+            constraints: Constraints::default(),
         }
     }
 
-    (matches, unmatched_givers, unmatched_receivers)
-}
+    fn pairs(pairs: &[(u32, u32)]) -> Vec<PairConstraint> {
+        pairs
+            .iter()
+            .map(|(giver_id, receiver_id)| PairConstraint {
+                giver_id: *giver_id,
+                receiver_id: *receiver_id,
+            })
+            .collect()
+    }
 
-fn get_shuffled_vector<T: Clone>(vec: &Vec<T>, rng: &mut impl Rng) -> Vec<T> {
-    let mut cloned_vec = vec.clone();
-    cloned_vec.shuffle(rng);
-    cloned_vec
-}
+    fn options(strategy: MatchingStrategy, cross_team_round: bool) -> MatchingOptions {
+        MatchingOptions {
+            cross_team_round,
+            strategy,
+            number_of_tries: NUMBER_OF_TRIES,
+            seed: 0,
+            reciprocal_matches: ReciprocalMatches::Allow,
+            reverse_matches_as_recent: false,
+            objective: Objective::Mean,
+            score_floor: 0,
+            as_of: date!(2024 - 03 - 07),
+        }
+    }
 
-fn get_optimal_receiver_index_and_score(
-    last_match_map: &HashMap<(u32, u32), i64>,
-    giver_id: u32,
-    unmatched_receivers: &Vec<MatchParticipant>,
-) -> Option<(usize, i64)> {
-    let mut best_receiver_score = i64::MIN;
-    let mut best_receiver_index_and_score = None;
-
-    for (i, receiver) in unmatched_receivers.iter().enumerate() {
-        if giver_id == receiver.id {
-            continue;
+    fn assert_everyone_matched_once(matching_round: &MatchingRound, ids: &[u32]) {
+        let mut givers: Vec<u32> = matching_round.matches.iter().map(|m| m.giver.id).collect();
+        let mut receivers: Vec<u32> = matching_round
+            .matches
+            .iter()
+            .map(|m| m.receiver.id)
+            .collect();
+        givers.sort();
+        receivers.sort();
+
+        assert_eq!(givers, ids);
+        assert_eq!(receivers, ids);
+        assert!(matching_round
+            .matches
+            .iter()
+            .all(|m| m.giver.id != m.receiver.id));
+    }
+
+    #[test]
+    fn test_optimal_strategy_matches_everyone_in_team() {
+        let participants_file = participants_file(vec![vec![1, 2, 3, 4, 5], vec![6, 7]]);
+
+        let matching_round = match_participants(
+            &participants_file,
+            &[],
+            &options(MatchingStrategy::Optimal, false),
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        assert_everyone_matched_once(&matching_round, &[1, 2, 3, 4, 5, 6, 7]);
+        assert!(matching_round
+            .matches
+            .iter()
+            .all(|m| m.giver.group_id == m.receiver.group_id));
+    }
+
+    #[test]
+    fn test_optimal_strategy_avoids_past_matches() {
+        let participants_file = participants_file(vec![vec![1, 2, 3]]);
+        let options = options(MatchingStrategy::Optimal, false);
+        let first_round = match_participants(
+            &participants_file,
+            &[],
+            &options,
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        let second_round = match_participants(
+            &participants_file,
+            &[first_round],
+            &options,
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        assert_everyone_matched_once(&second_round, &[1, 2, 3]);
+        assert!(second_round
+            .matches
+            .iter()
+            .all(|m| m.score == crate::MAX_SCORE));
+    }
+
+    #[test]
+    fn test_optimal_strategy_matches_single_participants_cross_team() {
+        let participants_file = participants_file(vec![vec![1], vec![2], vec![3]]);
+
+        let matching_round = match_participants(
+            &participants_file,
+            &[],
+            &options(MatchingStrategy::Optimal, true),
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        assert_everyone_matched_once(&matching_round, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_rotation_strategy_cycles_through_receivers() {
+        let participants_file = participants_file(vec![vec![1, 2, 3, 4]]);
+        let options = options(MatchingStrategy::Rotation, false);
+        let mut past_matching_rounds = vec![];
+
+        for _ in 0..3 {
+            let matching_round = match_participants(
+                &participants_file,
+                &past_matching_rounds,
+                &options,
+                &MatchingConstraints::default(),
+                &ScoringConfig::default(),
+            );
+
+            assert_everyone_matched_once(&matching_round, &[1, 2, 3, 4]);
+            assert!(matching_round
+                .matches
+                .iter()
+                .all(|m| m.score == crate::MAX_SCORE));
+
+            past_matching_rounds.push(matching_round);
         }
+    }
 
-        let score = get_days_since_last_match(last_match_map, giver_id, receiver.id);
+    #[test]
+    fn test_scores_count_the_days_until_the_as_of_date() {
+        let participants_file = participants_file(vec![vec![1, 2]]);
+        let options = options(MatchingStrategy::Optimal, false);
+        let first_round = match_participants(
+            &participants_file,
+            &[],
+            &options,
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        let second_round = match_participants(
+            &participants_file,
+            &[first_round],
+            &MatchingOptions {
+                as_of: date!(2024 - 04 - 04),
+                ..options
+            },
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        assert_eq!(second_round.date, date!(2024 - 04 - 04));
+        assert!(second_round.matches.iter().all(|m| m.score == 28));
+    }
 
-        if best_receiver_score < score {
-            best_receiver_score = score;
-            best_receiver_index_and_score = Some((i, score));
+    #[test]
+    fn test_same_seed_reproduces_greedy_round() {
+        let participants_file = participants_file(vec![vec![1, 2, 3, 4, 5, 6], vec![7, 8, 9]]);
+        let options = MatchingOptions {
+            seed: 42,
+            ..options(MatchingStrategy::Greedy, false)
+        };
+        let pairs = |matching_round: &MatchingRound| {
+            matching_round
+                .matches
+                .iter()
+                .map(|m| (m.giver.id, m.receiver.id))
+                .collect::<Vec<(u32, u32)>>()
+        };
+
+        let first_round = match_participants(
+            &participants_file,
+            &[],
+            &options,
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+        let second_round = match_participants(
+            &participants_file,
+            &[],
+            &options,
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        assert_eq!(pairs(&first_round), pairs(&second_round));
+        assert_eq!(first_round.seed, Some(42));
+        assert_eq!(first_round.strategy, Some(MatchingStrategy::Greedy));
+        assert_eq!(first_round.number_of_tries, Some(NUMBER_OF_TRIES));
+    }
+
+    #[test]
+    fn test_all_strategies_honor_constraints() {
+        let mut participants_file = participants_file(vec![vec![1, 2, 3, 4, 5]]);
+        participants_file.constraints = Constraints {
+            forbidden_pairs: pairs(&[(1, 2), (2, 3), (3, 4)]),
+            required_pairs: pairs(&[(5, 1)]),
+        };
+        let constraints = MatchingConstraints::new(&participants_file, false).unwrap();
+
+        for strategy in [
+            MatchingStrategy::Greedy,
+            MatchingStrategy::Optimal,
+            MatchingStrategy::Rotation,
+        ] {
+            let matching_round = match_participants(
+                &participants_file,
+                &[],
+                &options(strategy, false),
+                &constraints,
+                &ScoringConfig::default(),
+            );
+            let pairs: Vec<(u32, u32)> = matching_round
+                .matches
+                .iter()
+                .map(|m| (m.giver.id, m.receiver.id))
+                .collect();
+
+            assert!(pairs.contains(&(5, 1)), "{strategy:?}: {pairs:?}");
+            assert!(
+                pairs
+                    .iter()
+                    .all(|pair| ![(1, 2), (2, 3), (3, 4)].contains(pair)),
+                "{strategy:?}: {pairs:?}"
+            );
         }
     }
 
-    best_receiver_index_and_score
-}
+    #[test]
+    fn test_conflicting_constraints_are_reported() {
+        let mut participants_file = participants_file(vec![vec![1, 2, 3], vec![4, 5]]);
+        participants_file.constraints = Constraints {
+            forbidden_pairs: pairs(&[(1, 2)]),
+            required_pairs: pairs(&[(1, 2), (3, 4), (3, 5), (9, 1)]),
+        };
 
-fn create_match(giver: MatchParticipant, receiver: MatchParticipant, score: i64) -> Match {
-    return Match {
-        giver,
-        receiver,
-        score,
-    };
-}
+        let errors = MatchingConstraints::new(&participants_file, false).unwrap_err();
 
-fn score_matches(matches: &Vec<Match>, number_of_participants: usize) -> i64 {
-    let days_since_last_matches: Vec<i64> = matches.iter().map(|m| m.score).collect();
-    let sum: i64 = days_since_last_matches.iter().sum();
+        assert_eq!(errors.len(), 5, "{errors:#?}");
+    }
 
-    if number_of_participants == 0 {
-        0
-    } else {
-        sum / number_of_participants as i64
+    #[test]
+    fn test_all_strategies_avoid_reciprocal_matches() {
+        let participants_file = participants_file(vec![vec![1, 2, 3, 4]]);
+
+        for strategy in [
+            MatchingStrategy::Greedy,
+            MatchingStrategy::Optimal,
+            MatchingStrategy::Rotation,
+        ] {
+            for reciprocal_matches in [ReciprocalMatches::Penalize, ReciprocalMatches::Forbid] {
+                let options = MatchingOptions {
+                    reciprocal_matches,
+                    ..options(strategy, false)
+                };
+                let mut past_matching_rounds = vec![];
+
+                // Later rounds have fewer fresh receivers left, which makes reciprocal matches tempting
+                for _ in 0..3 {
+                    let matching_round = match_participants(
+                        &participants_file,
+                        &past_matching_rounds,
+                        &options,
+                        &MatchingConstraints::default(),
+                        &ScoringConfig::default(),
+                    );
+
+                    assert_eq!(
+                        count_reciprocal_matches(&matching_round.matches),
+                        0,
+                        "{strategy:?} {reciprocal_matches:?}"
+                    );
+                    past_matching_rounds.push(matching_round);
+                }
+            }
+        }
     }
-}
 
-fn get_next_matching_round_id(past_matching_rounds: &Vec<MatchingRound>) -> i32 {
-    let last_matching_round = past_matching_rounds.last();
+    #[test]
+    fn test_penalized_reciprocal_matches_are_used_to_match_everyone() {
+        let participants_file = participants_file(vec![vec![1, 2]]);
+        let options = MatchingOptions {
+            reciprocal_matches: ReciprocalMatches::Penalize,
+            ..options(MatchingStrategy::Optimal, false)
+        };
+
+        let matching_round = match_participants(
+            &participants_file,
+            &[],
+            &options,
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        assert_everyone_matched_once(&matching_round, &[1, 2]);
+    }
 
-    return match last_matching_round {
-        Some(matching_round) => matching_round.id + 1,
-        None => 1,
-    };
+    #[test]
+    fn test_reverse_matches_count_as_recent() {
+        let participants_file = participants_file(vec![vec![1, 2, 3]]);
+        let options = MatchingOptions {
+            reverse_matches_as_recent: true,
+            ..options(MatchingStrategy::Optimal, false)
+        };
+        let first_round = match_participants(
+            &participants_file,
+            &[],
+            &options,
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        let second_round = match_participants(
+            &participants_file,
+            &[first_round],
+            &options,
+            &MatchingConstraints::default(),
+            &ScoringConfig::default(),
+        );
+
+        assert!(second_round
+            .matches
+            .iter()
+            .all(|m| m.score < crate::MAX_SCORE));
+    }
 }
-
-// TODO add tests
@@ -0,0 +1,211 @@
+use super::messages::Message;
+use crate::error::Error;
+use crate::file_io::write_atomically;
+use crate::structs::participant::MatchParticipant;
+
+use std::collections::HashMap;
+use std::fs;
+use time::format_description::well_known::Rfc2822;
+use time::OffsetDateTime;
+
+/// How the messages of a round are output
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageFormat {
+    /// Print all messages to the console
+    #[default]
+    Print,
+    /// One text file per giver
+    Text,
+    /// One Markdown file per giver
+    Markdown,
+    /// One email draft per giver, addressed to the giver
+    Eml,
+    /// A single JSON array with the giver, receiver and text of every message
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageOutput {
+    pub format: MessageFormat,
+    /// The directory the files are written to
+    pub directory: String,
+    /// The From address of the email drafts
+    pub sender: Option<String>,
+    /// The current email addresses the drafts are addressed to, by participant id
+    pub emails: HashMap<u32, String>,
+}
+
+/// Writes the messages in the format of the output and returns the paths of the written files
+pub fn export_messages(messages: &[Message], output: &MessageOutput) -> Result<Vec<String>, Error> {
+    fs::create_dir_all(&output.directory).map_err(|e| Error::io(&output.directory, e))?;
+
+    if output.format == MessageFormat::Json {
+        let file_path = format!("{}/messages.json", output.directory);
+        let contents =
+            serde_json::to_string_pretty(messages).map_err(|e| Error::json(&file_path, e))?;
+        write_atomically(&file_path, &contents)?;
+
+        return Ok(vec![file_path]);
+    }
+
+    let date = OffsetDateTime::now_utc();
+
+    messages
+        .iter()
+        .map(|message| {
+            let (extension, contents) = match output.format {
+                MessageFormat::Markdown => ("md", to_markdown(message)),
+                MessageFormat::Eml => {
+                    let email = output.emails.get(&message.giver.id).map(|e| e.as_str());
+                    (
+                        "eml",
+                        to_eml(message, output.sender.as_deref(), email, date),
+                    )
+                }
+                _ => ("txt", message.text.clone()),
+            };
+
+            let file_path = format!(
+                "{}/{}.{extension}",
+                output.directory,
+                get_file_name(&message.giver)
+            );
+            write_atomically(&file_path, &contents)?;
+
+            Ok(file_path)
+        })
+        .collect()
+}
+
+/// The id keeps the names unique, the name makes them easy to find
+pub fn get_file_name(participant: &MatchParticipant) -> String {
+    let name: String = participant
+        .full_name()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+
+    format!("{}_{name}", participant.id)
+}
+
+fn to_markdown(message: &Message) -> String {
+    format!(
+        "# {}\n\n{}\n",
+        message.giver.get_locale().message_subject(),
+        message.text.trim_end()
+    )
+}
+
+/// An RFC 5322 message that email clients open as an unsent draft. Givers without an email
+/// address get a draft without recipient.
+fn to_eml(
+    message: &Message,
+    sender: Option<&str>,
+    email: Option<&str>,
+    date: OffsetDateTime,
+) -> String {
+    let giver = &message.giver;
+    let mut headers = vec![];
+
+    if let Some(sender) = sender {
+        headers.push(format!("From: {sender}"));
+    }
+
+    if let Some(email) = email {
+        headers.push(format!(
+            "To: {} <{email}>",
+            encode_display_name(&giver.full_name())
+        ));
+    }
+
+    headers.push(format!(
+        "Subject: {}",
+        encode_word(giver.get_locale().message_subject())
+    ));
+    headers.push(format!(
+        "Date: {}",
+        date.format(&Rfc2822).expect("Failed to format the date")
+    ));
+    headers.push("MIME-Version: 1.0".to_string());
+    headers.push("Content-Type: text/plain; charset=UTF-8".to_string());
+    headers.push("Content-Transfer-Encoding: 8bit".to_string());
+    headers.push("X-Unsent: 1".to_string());
+
+    let body = message.text.trim_end().lines().collect::<Vec<&str>>();
+
+    format!("{}\r\n\r\n{}\r\n", headers.join("\r\n"), body.join("\r\n"))
+}
+
+fn encode_display_name(name: &str) -> String {
+    match name.is_ascii() {
+        true => format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\"")),
+        false => encode_word(name),
+    }
+}
+
+/// Header values may only contain ASCII, other text is sent as a "Q" encoded word (RFC 2047)
+fn encode_word(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+
+    let encoded: String = text
+        .bytes()
+        .map(|byte| match byte {
+            b' ' => "_".to_string(),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'!' | b'*' | b'+' | b'-' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("={byte:02X}"),
+        })
+        .collect();
+
+    format!("=?UTF-8?Q?{encoded}?=")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::locale::Locale;
+    use crate::test_fixtures::match_participant;
+    use time::macros::datetime;
+
+    fn participant(first_name: &str) -> MatchParticipant {
+        MatchParticipant {
+            first_name: first_name.to_string(),
+            last_name: "Müller".to_string(),
+            locale: Some(Locale::De),
+            ..match_participant(1, 1)
+        }
+    }
+
+    #[test]
+    fn test_eml_draft() {
+        let message = Message {
+            giver: participant("Anna"),
+            receiver: participant("Cara"),
+            text: "Hi Anna\nDein Feedbackempfänger ist Cara.\n\n".to_string(),
+        };
+
+        assert_eq!(
+            to_eml(
+                &message,
+                Some("team@example.com"),
+                Some("anna@example.com"),
+                datetime!(2024-03-07 10:00 UTC)
+            ),
+            "From: team@example.com\r\n\
+             To: =?UTF-8?Q?Anna_M=C3=BCller?= <anna@example.com>\r\n\
+             Subject: =?UTF-8?Q?Dein_Feedbackempf=C3=A4nger?=\r\n\
+             Date: Thu, 07 Mar 2024 10:00:00 +0000\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=UTF-8\r\n\
+             Content-Transfer-Encoding: 8bit\r\n\
+             X-Unsent: 1\r\n\
+             \r\n\
+             Hi Anna\r\n\
+             Dein Feedbackempfänger ist Cara.\r\n"
+        );
+        assert_eq!(get_file_name(&message.giver), "1_Anna_Müller");
+    }
+}
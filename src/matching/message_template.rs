@@ -0,0 +1,210 @@
+use crate::error::Error;
+use crate::structs::locale::Locale;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The templates used if the data directory has none, the German one is the message the tool
+/// always printed, including the team's Thursday appointment
+const GERMAN_TEMPLATE: &str = include_str!("../../data/templates/message.txt");
+const ENGLISH_TEMPLATE: &str = include_str!("../../data/templates/message_en.txt");
+
+const TEMPLATE_DIRECTORY: &str = "templates";
+
+/// Every placeholder a template can use, e.g. `{giver_first_name}`
+pub const PLACEHOLDERS: &[&str] = &[
+    "giver_first_name",
+    "giver_full_name",
+    "receiver_first_name",
+    "receiver_full_name",
+    "receiver_accusative",
+    "receiver_dative",
+    "number_of_weeks",
+    "intervall_weeks",
+    "feedback_date",
+    "group",
+    "receiver_group",
+];
+
+/// The message templates of the data directory, named `message[_group_<id>][_<locale>].txt`.
+/// `templates/message.txt` replaces the built-in German template, `message_en.txt` the English
+/// one and `templates/message_group_<id>.txt` replaces them for the givers of a single group.
+#[derive(Debug, Clone, Default)]
+pub struct MessageTemplates {
+    templates: HashMap<(Option<i32>, Locale), String>,
+}
+
+impl MessageTemplates {
+    /// Reads and checks the templates in the data directory
+    pub fn load(data_directory: &str) -> Result<MessageTemplates, Error> {
+        let template_directory = Path::new(data_directory).join(TEMPLATE_DIRECTORY);
+        let mut templates = MessageTemplates::default();
+
+        let Ok(entries) = fs::read_dir(&template_directory) else {
+            return Ok(templates);
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let path_string = path.to_string_lossy().to_string();
+            let Some(key) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(parse_file_name)
+            else {
+                continue;
+            };
+
+            let template = fs::read_to_string(&path).map_err(|e| Error::io(&path_string, e))?;
+            check_template(&path_string, &template)?;
+
+            templates.templates.insert(key, template);
+        }
+
+        Ok(templates)
+    }
+
+    /// The template of the group in the locale, falling back to the template of the data
+    /// directory and then to the built-in one
+    pub fn get_template(&self, group_id: i32, locale: Locale) -> &str {
+        self.templates
+            .get(&(Some(group_id), locale))
+            .or_else(|| self.templates.get(&(None, locale)))
+            .map(|template| template.as_str())
+            .unwrap_or_else(|| built_in_template(locale))
+    }
+}
+
+fn built_in_template(locale: Locale) -> &'static str {
+    match locale {
+        Locale::De => GERMAN_TEMPLATE,
+        Locale::En => ENGLISH_TEMPLATE,
+    }
+}
+
+/// Reads the group id and locale from a template file name, German if the locale is omitted
+fn parse_file_name(file_name: &str) -> Option<(Option<i32>, Locale)> {
+    let mut name = file_name.strip_prefix("message")?.strip_suffix(".txt")?;
+    let mut locale = Locale::De;
+
+    for candidate in [Locale::De, Locale::En] {
+        if let Some(rest) = name.strip_suffix(&format!("_{}", candidate.code())) {
+            name = rest;
+            locale = candidate;
+        }
+    }
+
+    match name {
+        "" => Some((None, locale)),
+        _ => {
+            let group_id = name.strip_prefix("_group_")?.parse::<i32>().ok()?;
+            Some((Some(group_id), locale))
+        }
+    }
+}
+
+fn check_template(name: &str, template: &str) -> Result<(), Error> {
+    let values: HashMap<&str, String> = PLACEHOLDERS
+        .iter()
+        .map(|placeholder| (*placeholder, String::new()))
+        .collect();
+
+    render(template, &values)
+        .map(|_| ())
+        .map_err(|errors| Error::Template {
+            path: name.to_string(),
+            errors,
+        })
+}
+
+/// Replaces every `{placeholder}` with its value, `{{` and `}}` are literal braces. Returns
+/// all unknown placeholders and unbalanced braces as errors.
+pub fn render(template: &str, values: &HashMap<&str, String>) -> Result<String, Vec<String>> {
+    let mut result = String::with_capacity(template.len());
+    let mut errors = Vec::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+
+                match (closed, values.get(name.as_str())) {
+                    (false, _) => errors.push(format!("The placeholder {{{name} isn't closed")),
+                    (true, Some(value)) => result.push_str(value),
+                    (true, None) => errors.push(format!("Unknown placeholder {{{name}}}")),
+                }
+            }
+            '}' => errors.push("A closing brace has no placeholder, use }} instead".to_string()),
+            c => result.push(c),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(result),
+        false => Err(errors),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_replaces_placeholders() {
+        let values = HashMap::from([("giver_first_name", "Anna".to_string())]);
+
+        assert_eq!(
+            render("Hi {giver_first_name} {{:}}", &values),
+            Ok("Hi Anna {:}".to_string())
+        );
+        assert_eq!(
+            render("Hi {giver} {giver_first_name", &values),
+            Err(vec![
+                "Unknown placeholder {giver}".to_string(),
+                "The placeholder {giver_first_name isn't closed".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_built_in_templates_are_valid() {
+        for locale in [Locale::De, Locale::En] {
+            assert!(check_template(locale.code(), built_in_template(locale)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(parse_file_name("message.txt"), Some((None, Locale::De)));
+        assert_eq!(parse_file_name("message_en.txt"), Some((None, Locale::En)));
+        assert_eq!(
+            parse_file_name("message_group_3.txt"),
+            Some((Some(3), Locale::De))
+        );
+        assert_eq!(
+            parse_file_name("message_group_3_en.txt"),
+            Some((Some(3), Locale::En))
+        );
+        assert_eq!(parse_file_name("message_group_x.txt"), None);
+        assert_eq!(parse_file_name("notes.txt"), None);
+    }
+}
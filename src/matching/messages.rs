@@ -1,57 +1,121 @@
-use time::{format_description, Duration};
-
-use crate::structs::{matching_round::MatchingRound, participant::Gender};
-
-pub fn print_messages_for_round(matching_round: &MatchingRound, intervall_weeks: i32) {
-    let feedback_date_string = feedback_date(matching_round, intervall_weeks);
-    let number_of_weeks = match intervall_weeks {
-        ..=2 => "zwei",
-        3 => "drei",
-        4 => "vier",
-        5 => "fünf",
-        6 => "sechs",
-        7 => "sieben",
-        8.. => "acht",
-    };
-    println!("\n");
-
-    for match_pair in &matching_round.matches {
-        let giver_first_name = &match_pair.giver.first_name;
-        let receiver_full_name = &match_pair.receiver.full_name();
-        let ihn_sie = match &match_pair.receiver.gender {
-            Gender::Male => "ihn",
-            Gender::Female => "sie",
-        };
-        let ihm_ihr = match &match_pair.receiver.gender {
-            Gender::Male => "ihm",
-            Gender::Female => "ihr",
-        };
-        println!(
-"Hi {giver_first_name} 😊 Dein Feedbackempfänger für die nächsten {number_of_weeks} Wochen ist {receiver_full_name}. \
-\n
-Deine Aufgabe ist es, die nächsten {number_of_weeks} Wochen etwas auf {ihn_sie} zu achten und {ihm_ihr} am Ende dieser {number_of_weeks} Wochen (z.B. am {feedback_date_string}) Feedback zu geben. \
-Das Feedback sollte im Idealfall so Sachen wie das Verhalten in und außerhalb von Meetings, Verhalten im Team, Code, Eigeninitiative etc. enthalten. \
-Mache bitte selber einen Termin mit {ihm_ihr} aus um {ihm_ihr} das Feedback zu geben. \
-Es gibt Donnerstags einen Blocker-Termin, den ihr dafür nutzen könnt. \
-\n
-Wenn dir aber schon vorher etwas auffällt, was du mitteilen möchtest, kannst du das gerne auch schon vorher tun! \
-Es ist auch nicht schlimm wenn dir mal nichts einfällt was du sagen kannst. Dann kannst du {ihn_sie} auch einfach fragen, ob es für {ihn_sie} ok ist, wenn ihr es ausfallen lasst. \
-\n
-Denk auch daran, dass du gerne Feedback zu einem bestimmten Thema oder einer bestimmten Frage einfordern kannst, wenn du das nächste Mal Feedback bekommst. \
-\n
-Viel Spaß 😊
----"
-      );
+use serde::Serialize;
+use time::Date;
+
+use super::feedback_date::{calculate_feedback_date, DEFAULT_FEEDBACK_WEEKDAY};
+use super::message_export::{export_messages, MessageFormat, MessageOutput};
+use super::message_template::{render, MessageTemplates};
+use crate::error::Error;
+use crate::structs::calendar::HolidayCalendar;
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participant::MatchParticipant;
+use std::collections::HashMap;
+
+/// The message for the giver of a match
+#[derive(Debug, Clone, Serialize)]
+pub struct Message {
+    pub giver: MatchParticipant,
+    pub receiver: MatchParticipant,
+    pub text: String,
+}
+
+/// Prints the messages or writes them to files, depending on the format of the output
+pub fn print_messages_for_round(
+    matching_round: &MatchingRound,
+    intervall_weeks: i32,
+    templates: &MessageTemplates,
+    output: &MessageOutput,
+) -> Result<(), Error> {
+    let messages = generate_messages(matching_round, intervall_weeks, templates);
+
+    if output.format == MessageFormat::Print {
+        println!("\n");
+
+        for message in messages {
+            println!("{}", message.text.trim_end());
+        }
+
+        return Ok(());
     }
+
+    let file_paths = export_messages(&messages, output)?;
+    println!(
+        "\nWrote {} message files: {:#?}",
+        file_paths.len(),
+        file_paths
+    );
+
+    Ok(())
 }
 
-fn feedback_date(matching_round: &MatchingRound, intervall_weeks: i32) -> String {
-    let feedback_weekday = 4;
-    let match_weekday = matching_round.date.weekday().number_days_from_sunday() as i32;
-    let days_to_add = (intervall_weeks * 7 + (feedback_weekday - match_weekday)) as i64;
-    let feedback_date = matching_round.date + Duration::days(days_to_add);
-    let format = format_description::parse("[day].[month].").unwrap();
-    feedback_date.format(&format).unwrap()
+/// Fills the template of the giver's group for every match of the round, in the order of the
+/// matches
+pub fn generate_messages(
+    matching_round: &MatchingRound,
+    intervall_weeks: i32,
+    templates: &MessageTemplates,
+) -> Vec<Message> {
+    matching_round
+        .matches
+        .iter()
+        .map(|match_pair| {
+            let giver = &match_pair.giver;
+            let receiver = &match_pair.receiver;
+            // The message is written in the language of the giver, who reads it
+            let locale = giver.get_locale();
+            let feedback_date = get_feedback_date(matching_round, giver.group_id, intervall_weeks);
+
+            // Without pronouns the receiver is referred to by name
+            let (accusative, dative) = match receiver.get_pronouns(locale) {
+                Some(pronouns) => (pronouns.accusative, pronouns.dative),
+                None => (receiver.first_name.clone(), receiver.first_name.clone()),
+            };
+
+            let values = HashMap::from([
+                ("giver_first_name", giver.first_name.clone()),
+                ("giver_full_name", giver.full_name()),
+                ("receiver_first_name", receiver.first_name.clone()),
+                ("receiver_full_name", receiver.full_name()),
+                ("receiver_accusative", accusative),
+                ("receiver_dative", dative),
+                (
+                    "number_of_weeks",
+                    locale.number_word(intervall_weeks).to_string(),
+                ),
+                ("intervall_weeks", intervall_weeks.to_string()),
+                ("feedback_date", locale.format_day(feedback_date)),
+                ("group", giver.group_id.to_string()),
+                ("receiver_group", receiver.group_id.to_string()),
+            ]);
+
+            let text = render(templates.get_template(giver.group_id, locale), &values)
+                .expect("Templates are checked when they are loaded");
+
+            Message {
+                giver: giver.clone(),
+                receiver: receiver.clone(),
+                text,
+            }
+        })
+        .collect()
+}
+
+/// The feedback date that was stored for the group, rounds created before the dates were
+/// stored get the default weekday without any blocked days
+pub fn get_feedback_date(
+    matching_round: &MatchingRound,
+    group_id: i32,
+    intervall_weeks: i32,
+) -> Date {
+    matching_round
+        .get_feedback_date(group_id)
+        .unwrap_or_else(|| {
+            calculate_feedback_date(
+                matching_round.date,
+                intervall_weeks,
+                DEFAULT_FEEDBACK_WEEKDAY,
+                &HolidayCalendar::default(),
+            )
+        })
 }
 
 #[cfg(test)]
@@ -59,38 +123,109 @@ mod tests {
     use time::macros::date;
 
     use super::*;
-    use crate::structs::matching_round::MatchingRound;
+    use crate::structs::locale::Locale;
+    use crate::structs::matching_round::{GroupFeedbackDate, MatchingRound};
+    use crate::structs::participant::Gender;
+    use crate::structs::r#match::Match;
+    use crate::test_fixtures::{match_participant, matching_round};
 
     #[test]
     fn test_feedback_date_match_on_thursday() {
         let date = date!(2024 - 02 - 29);
-        let matching_round = MatchingRound {
-            id: 2,
-            date,
-            matches: vec![],
-        };
-        assert_eq!(feedback_date(&matching_round, 2), "14.03.");
+        let matching_round = matching_round(2, date, vec![]);
+        assert_eq!(
+            Locale::De.format_day(get_feedback_date(&matching_round, 1, 2)),
+            "14.03."
+        );
     }
 
     #[test]
     fn test_feedback_date_match_on_wednesday() {
         let date = date!(2024 - 02 - 28);
-        let matching_round = MatchingRound {
-            id: 2,
-            date,
-            matches: vec![],
-        };
-        assert_eq!(feedback_date(&matching_round, 2), "14.03.");
+        let matching_round = matching_round(2, date, vec![]);
+        assert_eq!(
+            Locale::De.format_day(get_feedback_date(&matching_round, 1, 2)),
+            "14.03."
+        );
     }
 
     #[test]
     fn test_feedback_date_match_on_friday() {
         let date = date!(2024 - 03 - 01);
+        let matching_round = matching_round(2, date, vec![]);
+        assert_eq!(
+            Locale::De.format_day(get_feedback_date(&matching_round, 1, 2)),
+            "14.03."
+        );
+    }
+
+    #[test]
+    fn test_feedback_date_in_english() {
+        let matching_round = matching_round(2, date!(2024 - 03 - 01), vec![]);
+        assert_eq!(
+            Locale::En.format_day(get_feedback_date(&matching_round, 1, 2)),
+            "March 14"
+        );
+        assert_eq!(Locale::En.number_word(9), "eight");
+    }
+
+    #[test]
+    fn test_built_in_german_message_is_unchanged() {
+        let matching_round = matching_round(
+            2,
+            date!(2024 - 03 - 01),
+            vec![Match {
+                giver: MatchParticipant {
+                    first_name: "Anna".to_string(),
+                    last_name: "B".to_string(),
+                    ..match_participant(1, 1)
+                },
+                receiver: MatchParticipant {
+                    first_name: "Ben".to_string(),
+                    last_name: "B".to_string(),
+                    gender: Gender::Male,
+                    ..match_participant(2, 1)
+                },
+                score: 0,
+            }],
+        );
+        let messages = generate_messages(&matching_round, 2, &MessageTemplates::default());
+
+        assert_eq!(
+            messages[0].text.trim_end(),
+            "Hi Anna 😊 Dein Feedbackempfänger für die nächsten zwei Wochen ist Ben B. \n\n\
+             Deine Aufgabe ist es, die nächsten zwei Wochen etwas auf ihn zu achten und ihm am Ende \
+             dieser zwei Wochen (z.B. am 14.03.) Feedback zu geben. \
+             Das Feedback sollte im Idealfall so Sachen wie das Verhalten in und außerhalb von \
+             Meetings, Verhalten im Team, Code, Eigeninitiative etc. enthalten. \
+             Mache bitte selber einen Termin mit ihm aus um ihm das Feedback zu geben. \
+             Es gibt Donnerstags einen Blocker-Termin, den ihr dafür nutzen könnt. \n\n\
+             Wenn dir aber schon vorher etwas auffällt, was du mitteilen möchtest, kannst du das \
+             gerne auch schon vorher tun! Es ist auch nicht schlimm wenn dir mal nichts einfällt \
+             was du sagen kannst. Dann kannst du ihn auch einfach fragen, ob es für ihn ok ist, \
+             wenn ihr es ausfallen lasst. \n\n\
+             Denk auch daran, dass du gerne Feedback zu einem bestimmten Thema oder einer \
+             bestimmten Frage einfordern kannst, wenn du das nächste Mal Feedback bekommst. \n\n\
+             Viel Spaß 😊\n---"
+        );
+    }
+
+    #[test]
+    fn test_stored_feedback_date_is_used() {
         let matching_round = MatchingRound {
-            id: 2,
-            date,
-            matches: vec![],
+            feedback_dates: vec![GroupFeedbackDate {
+                group_id: 1,
+                date: date!(2024 - 03 - 19),
+            }],
+            ..matching_round(2, date!(2024 - 03 - 01), vec![])
         };
-        assert_eq!(feedback_date(&matching_round, 2), "14.03.");
+        assert_eq!(
+            get_feedback_date(&matching_round, 1, 2),
+            date!(2024 - 03 - 19)
+        );
+        assert_eq!(
+            get_feedback_date(&matching_round, 2, 2),
+            date!(2024 - 03 - 14)
+        );
     }
 }
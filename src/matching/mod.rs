@@ -1,4 +1,21 @@
+pub mod assignment;
+pub mod calendar_export;
 pub mod complete_givers;
+pub mod constraints;
+pub mod feedback_date;
+pub mod greedy;
+pub mod history_export;
+pub mod html_report;
 pub mod last_match_map;
+pub mod matcher;
+#[allow(clippy::module_inception)]
 pub mod matching;
+pub mod message_export;
+pub mod message_template;
 pub mod messages;
+pub mod objective;
+pub mod optimal;
+pub mod rotation;
+pub mod round_summary;
+pub mod scoring;
+pub mod stats;
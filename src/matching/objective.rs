@@ -0,0 +1,320 @@
+use super::assignment::{solve_assignment, solve_square_assignment};
+
+use std::iter;
+
+/// What makes one matching better than another
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Objective {
+    /// Maximize the average score
+    #[default]
+    Mean,
+    /// Maximize the lowest score, then the average score
+    MaxMin,
+    /// Maximize the lowest score, then the second lowest score and so on
+    LexMaxMin,
+    /// Maximize the average score while keeping as many scores as possible above the floor
+    MeanWithFloor,
+}
+
+/// Rates the scores of a matching, a higher rating is better. Matchings with fewer matches
+/// are always rated lower for the fairness objectives, since the unmatched participants
+/// would be worse off than anyone else.
+pub fn rate_scores(
+    objective: Objective,
+    score_floor: i64,
+    scores: &[i64],
+    number_of_participants: usize,
+) -> Vec<i64> {
+    let sum: i64 = scores.iter().sum();
+    let mean = match number_of_participants {
+        0 => 0,
+        _ => sum / number_of_participants as i64,
+    };
+    let number_of_matches = scores.len() as i64;
+
+    match objective {
+        Objective::Mean => vec![mean],
+        Objective::MaxMin => {
+            let min = scores.iter().min().copied().unwrap_or(i64::MAX);
+            vec![number_of_matches, min, mean]
+        }
+        Objective::LexMaxMin => {
+            let mut sorted_scores = scores.to_vec();
+            sorted_scores.sort();
+            sorted_scores.insert(0, number_of_matches);
+            sorted_scores
+        }
+        Objective::MeanWithFloor => {
+            let scores_below_floor = scores.iter().filter(|s| **s < score_floor).count() as i64;
+            vec![number_of_matches, -scores_below_floor, mean]
+        }
+    }
+}
+
+/// Transforms the weights of an assignment problem, so that solving it for the highest sum of
+/// weights optimizes the objective instead
+pub fn apply_objective(
+    objective: Objective,
+    score_floor: i64,
+    weights: &[Vec<Option<i64>>],
+) -> Vec<Vec<Option<i64>>> {
+    match objective {
+        Objective::Mean => weights.to_vec(),
+        Objective::MaxMin => remove_weights_below_bottleneck(weights),
+        Objective::LexMaxMin => get_lexicographic_weights(weights),
+        Objective::MeanWithFloor => penalize_weights_below_floor(weights, score_floor),
+    }
+}
+
+fn count_matches(weights: &[Vec<Option<i64>>]) -> usize {
+    solve_assignment(weights)
+        .iter()
+        .filter(|receiver| receiver.is_some())
+        .count()
+}
+
+fn get_sorted_levels(weights: &[Vec<Option<i64>>]) -> Vec<i64> {
+    let mut levels: Vec<i64> = weights.iter().flatten().flatten().copied().collect();
+    levels.sort();
+    levels.dedup();
+    levels
+}
+
+/// Finds the highest weight that every matching with the maximum number of matches can use as
+/// its lowest weight (binary search over the distinct weights) and removes all lower weights
+fn remove_weights_below_bottleneck(weights: &[Vec<Option<i64>>]) -> Vec<Vec<Option<i64>>> {
+    let levels = get_sorted_levels(weights);
+    let max_number_of_matches = count_matches(weights);
+
+    let without_weights_below = |threshold: i64| -> Vec<Vec<Option<i64>>> {
+        weights
+            .iter()
+            .map(|row| row.iter().map(|w| w.filter(|w| *w >= threshold)).collect())
+            .collect()
+    };
+
+    let mut low = 0;
+    let mut high = levels.len();
+
+    while high - low > 1 {
+        let middle = (low + high) / 2;
+
+        if count_matches(&without_weights_below(levels[middle])) == max_number_of_matches {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    match levels.get(low) {
+        Some(bottleneck) => without_weights_below(*bottleneck),
+        None => weights.to_vec(),
+    }
+}
+
+/// Solves one level of weights at a time, starting with the unmatched participants and then
+/// from the lowest weight up: the number of pairs on the level is minimized among the pairs
+/// that keep the levels below optimal, and only the pairs that keep this level optimal as well
+/// are kept for the next one. The weights prefer the pairs of the resulting matching, then the
+/// other pairs that keep every level optimal, so solving them again after forbidding some pairs
+/// still stays close to the objective.
+fn get_lexicographic_weights(weights: &[Vec<Option<i64>>]) -> Vec<Vec<Option<i64>>> {
+    let levels = get_sorted_levels(weights);
+    let number_of_receivers = weights.iter().map(|row| row.len()).max().unwrap_or(0);
+    let size = weights.len().max(number_of_receivers);
+
+    // Forbidden pairs and the padding of the matrix are on the level `None`, below every weight
+    let level = |giver: usize, receiver: usize| -> Option<usize> {
+        let weight = (*weights.get(giver)?.get(receiver)?)?;
+        levels.binary_search(&weight).ok()
+    };
+
+    let mut kept_pairs = vec![vec![true; size]; size];
+    let mut assignment: Option<Vec<usize>> = None;
+
+    for current_level in iter::once(None).chain((0..levels.len()).map(Some)) {
+        // The matching already avoids the level, so no matching of the kept pairs needs it
+        if let Some(assignment) = &assignment {
+            if !(0..size).any(|giver| level(giver, assignment[giver]) == current_level) {
+                for (giver, row) in kept_pairs.iter_mut().enumerate() {
+                    for (receiver, kept) in row.iter_mut().enumerate() {
+                        *kept &= level(giver, receiver) != current_level;
+                    }
+                }
+                continue;
+            }
+        }
+
+        // Pairs that aren't kept cost more than any matching of the kept pairs
+        let costs: Vec<Vec<i64>> = (0..size)
+            .map(|giver| {
+                (0..size)
+                    .map(|receiver| match kept_pairs[giver][receiver] {
+                        false => size as i64 + 1,
+                        true => (level(giver, receiver) == current_level) as i64,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let (new_assignment, tight_pairs) = solve_square_assignment(&costs);
+
+        for (kept_row, tight_row) in kept_pairs.iter_mut().zip(tight_pairs) {
+            for (kept, tight) in kept_row.iter_mut().zip(tight_row) {
+                *kept &= tight;
+            }
+        }
+
+        assignment = Some(new_assignment);
+    }
+
+    let assignment = assignment.unwrap_or_default();
+
+    weights
+        .iter()
+        .enumerate()
+        .map(|(giver, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(receiver, weight)| {
+                    weight.map(|_| match kept_pairs[giver][receiver] {
+                        true if assignment[giver] == receiver => 2,
+                        true => 1,
+                        false => 0,
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Lowers every weight below the floor by so much, that avoiding one of them is worth more
+/// than any difference in the sum of the other weights
+fn penalize_weights_below_floor(
+    weights: &[Vec<Option<i64>>],
+    score_floor: i64,
+) -> Vec<Vec<Option<i64>>> {
+    let levels = get_sorted_levels(weights);
+    let (Some(min_weight), Some(max_weight)) = (levels.first(), levels.last()) else {
+        return weights.to_vec();
+    };
+    let penalty = (max_weight - min_weight + 1) * (weights.len() as i64 + 1);
+
+    weights
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|w| w.map(|w| if w < score_floor { w - penalty } else { w }))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assigned_weights(weights: &[Vec<Option<i64>>], objective: Objective) -> Vec<i64> {
+        let transformed_weights = apply_objective(objective, 30, weights);
+        let mut assigned: Vec<i64> = solve_assignment(&transformed_weights)
+            .iter()
+            .enumerate()
+            .filter_map(|(giver, receiver)| receiver.and_then(|r| weights[giver][r]))
+            .collect();
+        assigned.sort();
+        assigned
+    }
+
+    #[test]
+    fn test_objectives_trade_the_mean_for_the_lowest_score() {
+        // The best mean (100 + 100 + 10) leaves one score at 10, the fairest matching is 40s
+        let weights = vec![
+            vec![None, Some(100), Some(40)],
+            vec![Some(40), None, Some(100)],
+            vec![Some(10), Some(40), None],
+        ];
+
+        assert_eq!(
+            assigned_weights(&weights, Objective::Mean),
+            vec![10, 100, 100]
+        );
+        assert_eq!(
+            assigned_weights(&weights, Objective::MaxMin),
+            vec![40, 40, 40]
+        );
+        assert_eq!(
+            assigned_weights(&weights, Objective::LexMaxMin),
+            vec![40, 40, 40]
+        );
+        assert_eq!(
+            assigned_weights(&weights, Objective::MeanWithFloor),
+            vec![40, 40, 40]
+        );
+    }
+
+    #[test]
+    fn test_lex_max_min_improves_the_second_lowest_score() {
+        // Both matchings have the same lowest score, max-min then prefers the higher mean
+        let weights = vec![
+            vec![None, Some(5), Some(5)],
+            vec![Some(10), None, Some(6)],
+            vec![Some(20), Some(10), None],
+        ];
+
+        assert_eq!(
+            assigned_weights(&weights, Objective::MaxMin),
+            vec![5, 6, 20]
+        );
+        assert_eq!(
+            assigned_weights(&weights, Objective::LexMaxMin),
+            vec![5, 10, 10]
+        );
+    }
+
+    #[test]
+    fn test_lex_max_min_with_many_distinct_scores() {
+        // Every block of three is only matched within itself and prefers 5, 10, 10 over
+        // 5, 6, 20. The 25 distinct scores are more levels than penalties growing by the number
+        // of participants per level could represent.
+        let mut weights = vec![vec![None; 20]; 20];
+        for block in 0..6 {
+            let offset = block as i64 * 100;
+            let block_weights = [
+                [None, Some(5), Some(5)],
+                [Some(10), None, Some(6)],
+                [Some(20), Some(10), None],
+            ];
+
+            for (giver, row) in block_weights.iter().enumerate() {
+                for (receiver, weight) in row.iter().enumerate() {
+                    weights[block * 3 + giver][block * 3 + receiver] = weight.map(|w| w + offset);
+                }
+            }
+        }
+        weights[18][19] = Some(1000);
+        weights[19][18] = Some(1000);
+
+        let mut expected: Vec<i64> = (0..6)
+            .flat_map(|block| [5, 10, 10].map(|w| w + block * 100))
+            .chain([1000, 1000])
+            .collect();
+        expected.sort();
+
+        assert_eq!(assigned_weights(&weights, Objective::LexMaxMin), expected);
+    }
+
+    #[test]
+    fn test_rate_scores_prefers_more_matches_for_fairness_objectives() {
+        let rating = |objective, scores: &[i64]| rate_scores(objective, 50, scores, 3);
+
+        assert!(rating(Objective::MaxMin, &[10, 10, 10]) > rating(Objective::MaxMin, &[90, 90]));
+        assert!(
+            rating(Objective::LexMaxMin, &[10, 20, 30])
+                > rating(Objective::LexMaxMin, &[10, 15, 90])
+        );
+        assert!(
+            rating(Objective::MeanWithFloor, &[60, 60, 60])
+                > rating(Objective::MeanWithFloor, &[10, 200, 200])
+        );
+    }
+}
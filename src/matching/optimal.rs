@@ -0,0 +1,357 @@
+use crate::structs::participant::MatchParticipant;
+use crate::RECIPROCAL_PENALTY;
+
+use super::assignment::solve_assignment;
+use super::matcher::{
+    create_match, Matcher, MatchingContext, MatchingGroup, MatchingResult, ReciprocalMatches,
+};
+use super::objective::apply_objective;
+use rand::RngCore;
+
+/// How many assignments the search for a matching without reciprocal matches solves at most
+/// before it keeps the best matching found so far
+const MAX_SEARCHED_ASSIGNMENTS: usize = 1000;
+
+/// Finds the best possible matching for the objective by solving the assignment problem
+/// exactly
+pub struct OptimalMatcher {}
+
+impl Matcher for OptimalMatcher {
+    fn find_matches(
+        &self,
+        matching_groups: &[MatchingGroup],
+        context: &MatchingContext,
+        _rng: &mut dyn RngCore,
+    ) -> MatchingResult {
+        if context.cross_team_round {
+            let participants: Vec<MatchParticipant> = matching_groups
+                .iter()
+                .flat_map(|g| g.participants.clone())
+                .collect();
+
+            return get_optimal_matches(&participants, context);
+        }
+
+        let mut result = MatchingResult::default();
+
+        for group in matching_groups {
+            let mut group_result = get_optimal_matches(&group.participants, context);
+
+            result.append(&mut group_result);
+        }
+
+        result
+    }
+}
+
+/// Matches every participant as giver and receiver so that the sum of the scores is maximal.
+/// In cross-team rounds in-team pairs that already happened are only used if no cross-team
+/// pair is left, mirroring the two phases of the greedy matching.
+fn get_optimal_matches(
+    participants: &[MatchParticipant],
+    context: &MatchingContext,
+) -> MatchingResult {
+    let weights = apply_objective(
+        context.objective,
+        context.score_floor,
+        &get_weights(participants, context),
+    );
+    let assignment = avoid_reciprocal_matches(
+        &weights,
+        context.reciprocal_matches,
+        MAX_SEARCHED_ASSIGNMENTS,
+    );
+
+    let mut result = MatchingResult::default();
+    let mut matched_receivers = vec![false; participants.len()];
+
+    for (giver_index, receiver_index) in assignment.into_iter().enumerate() {
+        let giver = &participants[giver_index];
+
+        match receiver_index {
+            Some(receiver_index) => {
+                let receiver = &participants[receiver_index];
+                let score = context.scorer.score(giver, receiver);
+                matched_receivers[receiver_index] = true;
+                result
+                    .matches
+                    .push(create_match(giver.clone(), receiver.clone(), score));
+            }
+            None => result.unmatched_givers.push(giver.clone()),
+        }
+    }
+
+    result.unmatched_receivers = participants
+        .iter()
+        .zip(matched_receivers)
+        .filter(|(_, matched)| !matched)
+        .map(|(receiver, _)| receiver.clone())
+        .collect();
+
+    result
+}
+
+/// Reciprocal matches can't be expressed in the assignment problem, so they are removed by a
+/// branch and bound search: a matching without the reciprocal match of two participants has
+/// to leave out one of its directions, so both are forbidden in turn and the assignment is
+/// solved again. When reciprocal matches are only penalized, keeping both directions is the
+/// third branch. The search is exponential in the worst case, so it stops after the maximum
+/// number of assignments and the result may not be the best matching for large groups.
+fn avoid_reciprocal_matches(
+    weights: &[Vec<Option<i64>>],
+    reciprocal_matches: ReciprocalMatches,
+    max_searched_assignments: usize,
+) -> Vec<Option<usize>> {
+    if reciprocal_matches == ReciprocalMatches::Allow {
+        return solve_assignment(weights);
+    }
+
+    let mut best = None;
+    let mut remaining_assignments = max_searched_assignments;
+    search_assignments(
+        weights,
+        &[],
+        reciprocal_matches,
+        &mut best,
+        &mut remaining_assignments,
+    );
+
+    best.map(|(_, assignment)| assignment)
+        .expect("Forbidding every reciprocal match leaves an assignment")
+}
+
+type RatedAssignment = ((usize, i64), Vec<Option<usize>>);
+
+fn search_assignments(
+    weights: &[Vec<Option<i64>>],
+    kept_pairs: &[(usize, usize)],
+    reciprocal_matches: ReciprocalMatches,
+    best: &mut Option<RatedAssignment>,
+    remaining_assignments: &mut usize,
+) {
+    // The first branch always leads to a matching, so the search only stops once there is one
+    if *remaining_assignments == 0 && best.is_some() {
+        return;
+    }
+    *remaining_assignments = remaining_assignments.saturating_sub(1);
+
+    let assignment = solve_assignment(weights);
+    let reciprocal_pairs = get_reciprocal_pairs(&assignment);
+    let penalty = 2 * reciprocal_pairs.len() as i64 * RECIPROCAL_PENALTY;
+    let (number_of_matches, sum_of_weights) = rate_assignment(weights, &assignment);
+
+    // No assignment of this branch is better than the one without its restrictions
+    if best
+        .as_ref()
+        .is_some_and(|(rating, _)| (number_of_matches, sum_of_weights) <= *rating)
+    {
+        return;
+    }
+
+    if reciprocal_pairs.is_empty() || reciprocal_matches == ReciprocalMatches::Penalize {
+        let rating = (number_of_matches, sum_of_weights - penalty);
+
+        let is_better = match best {
+            Some((best_rating, _)) => rating > *best_rating,
+            None => true,
+        };
+
+        if is_better {
+            *best = Some((rating, assignment.clone()));
+        }
+    }
+
+    let Some(&(first, second)) = reciprocal_pairs
+        .iter()
+        .find(|pair| !kept_pairs.contains(pair))
+    else {
+        return;
+    };
+
+    for (giver, receiver) in [(first, second), (second, first)] {
+        let mut new_weights = weights.to_vec();
+        new_weights[giver][receiver] = None;
+        search_assignments(
+            &new_weights,
+            kept_pairs,
+            reciprocal_matches,
+            best,
+            remaining_assignments,
+        );
+    }
+
+    if reciprocal_matches == ReciprocalMatches::Penalize {
+        let mut new_weights = weights.to_vec();
+
+        for (giver, receiver) in [(first, second), (second, first)] {
+            for (index, row) in new_weights.iter_mut().enumerate() {
+                if index != giver {
+                    row[receiver] = None;
+                }
+            }
+            for (index, weight) in new_weights[giver].iter_mut().enumerate() {
+                if index != receiver {
+                    *weight = None;
+                }
+            }
+        }
+
+        let mut new_kept_pairs = kept_pairs.to_vec();
+        new_kept_pairs.push((first, second));
+        search_assignments(
+            &new_weights,
+            &new_kept_pairs,
+            reciprocal_matches,
+            best,
+            remaining_assignments,
+        );
+    }
+}
+
+fn get_weights(
+    participants: &[MatchParticipant],
+    context: &MatchingContext,
+) -> Vec<Vec<Option<i64>>> {
+    let scores: Vec<Vec<Option<i64>>> = participants
+        .iter()
+        .map(|giver| {
+            participants
+                .iter()
+                .map(|receiver| {
+                    (giver.id != receiver.id
+                        && context.constraints.is_allowed(giver.id, receiver.id))
+                    .then(|| context.scorer.score(giver, receiver))
+                })
+                .collect()
+        })
+        .collect();
+
+    if !context.cross_team_round {
+        return scores;
+    }
+
+    // Lower in-team pairs that already happened below every other pair
+    let max_score = scores
+        .iter()
+        .flatten()
+        .flatten()
+        .max()
+        .copied()
+        .unwrap_or(0);
+    let min_score = scores
+        .iter()
+        .flatten()
+        .flatten()
+        .min()
+        .copied()
+        .unwrap_or(0);
+    let in_team_penalty = max_score - min_score + 1;
+
+    participants
+        .iter()
+        .zip(scores)
+        .map(|(giver, giver_scores)| {
+            participants
+                .iter()
+                .zip(giver_scores)
+                .map(|(receiver, score)| {
+                    let score = score?;
+
+                    if giver.group_id == receiver.group_id
+                        && context.scorer.has_matched(giver.id, receiver.id)
+                    {
+                        return Some(score - in_team_penalty);
+                    }
+
+                    Some(score)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The index pairs of all participants that are matched with each other
+fn get_reciprocal_pairs(assignment: &[Option<usize>]) -> Vec<(usize, usize)> {
+    assignment
+        .iter()
+        .enumerate()
+        .filter_map(|(giver, receiver)| {
+            let receiver = (*receiver)?;
+            (giver < receiver && assignment[receiver] == Some(giver)).then_some((giver, receiver))
+        })
+        .collect()
+}
+
+/// Rates an assignment by its number of matches first and its sum of weights second
+fn rate_assignment(weights: &[Vec<Option<i64>>], assignment: &[Option<usize>]) -> (usize, i64) {
+    let number_of_matches = assignment.iter().filter(|r| r.is_some()).count();
+    let sum_of_weights: i64 = assignment
+        .iter()
+        .enumerate()
+        .filter_map(|(giver, receiver)| receiver.and_then(|r| weights[giver][r]))
+        .sum();
+
+    (number_of_matches, sum_of_weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forbidden_reciprocal_matches_keep_everyone_matched() {
+        // Forbidding the weaker direction of the reciprocal matches one after the other
+        // leaves the last giver without receiver
+        let weights = vec![
+            vec![None, Some(1), None, Some(7)],
+            vec![Some(6), None, Some(1), Some(6)],
+            vec![Some(0), Some(4), None, Some(3)],
+            vec![Some(1), Some(0), None, None],
+        ];
+
+        let assignment = avoid_reciprocal_matches(
+            &weights,
+            ReciprocalMatches::Forbid,
+            MAX_SEARCHED_ASSIGNMENTS,
+        );
+
+        assert_eq!(assignment, vec![Some(3), Some(2), Some(0), Some(1)]);
+        assert!(get_reciprocal_pairs(&assignment).is_empty());
+    }
+
+    #[test]
+    fn test_reciprocal_search_in_a_large_group() {
+        // Symmetric weights make every best assignment full of reciprocal matches
+        let size = 40;
+        let weights: Vec<Vec<Option<i64>>> = (0..size)
+            .map(|giver| {
+                (0..size)
+                    .map(|receiver| {
+                        (giver != receiver).then_some(((giver + receiver) * 7 % 11) as i64)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let best = avoid_reciprocal_matches(
+            &weights,
+            ReciprocalMatches::Forbid,
+            MAX_SEARCHED_ASSIGNMENTS,
+        );
+        // Without budget the search stops at the first matching without reciprocal matches
+        let first = avoid_reciprocal_matches(&weights, ReciprocalMatches::Forbid, 0);
+
+        for assignment in [&best, &first] {
+            assert!(assignment.iter().all(|receiver| receiver.is_some()));
+            assert!(get_reciprocal_pairs(assignment).is_empty());
+        }
+        assert!(rate_assignment(&weights, &best) >= rate_assignment(&weights, &first));
+
+        let penalized = avoid_reciprocal_matches(
+            &weights,
+            ReciprocalMatches::Penalize,
+            MAX_SEARCHED_ASSIGNMENTS,
+        );
+        assert!(penalized.iter().all(|receiver| receiver.is_some()));
+    }
+}
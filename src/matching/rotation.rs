@@ -0,0 +1,124 @@
+use crate::structs::participant::MatchParticipant;
+
+use super::matcher::{
+    create_match, Matcher, MatchingContext, MatchingGroup, MatchingResult, ReciprocalMatches,
+};
+use rand::RngCore;
+
+/// Sorts the participants by id and lets everyone give feedback to the participant a fixed
+/// number of places further down the list. The offset is derived from the matching round id,
+/// so consecutive rounds cycle through all receivers without looking at the history. If the
+/// offset of the round breaks a constraint or creates unwanted reciprocal matches, the next
+/// offset that doesn't is used instead. The objective is ignored.
+pub struct RotationMatcher {}
+
+impl Matcher for RotationMatcher {
+    fn find_matches(
+        &self,
+        matching_groups: &[MatchingGroup],
+        context: &MatchingContext,
+        _rng: &mut dyn RngCore,
+    ) -> MatchingResult {
+        if context.cross_team_round {
+            let participants: Vec<MatchParticipant> = matching_groups
+                .iter()
+                .flat_map(|g| g.participants.clone())
+                .collect();
+
+            return get_rotated_matches(participants, context);
+        }
+
+        let mut result = MatchingResult::default();
+
+        for group in matching_groups {
+            let mut group_result = get_rotated_matches(group.participants.clone(), context);
+
+            result.append(&mut group_result);
+        }
+
+        result
+    }
+}
+
+fn get_rotated_matches(
+    mut participants: Vec<MatchParticipant>,
+    context: &MatchingContext,
+) -> MatchingResult {
+    let number_of_participants = participants.len();
+
+    if number_of_participants < 2 {
+        return MatchingResult {
+            matches: vec![],
+            unmatched_givers: participants.clone(),
+            unmatched_receivers: participants,
+        };
+    }
+
+    participants.sort_by_key(|p| (p.group_id, p.id));
+
+    let number_of_offsets = number_of_participants - 1;
+    let first_offset =
+        (context.matching_round_id - 1).rem_euclid(number_of_offsets as i32) as usize;
+
+    let offset = (0..number_of_offsets)
+        .map(|i| (first_offset + i) % number_of_offsets + 1)
+        .find(|offset| is_rotation_allowed(&participants, *offset, context))
+        .unwrap_or(first_offset + 1);
+
+    get_matches_for_offset(&participants, offset, context)
+}
+
+/// Rotating by half of the participants lets every pair give feedback to each other. The
+/// offsets are always between 1 and the number of participants minus 1.
+fn is_reciprocal_offset(number_of_participants: usize, offset: usize) -> bool {
+    2 * offset == number_of_participants
+}
+
+fn is_rotation_allowed(
+    participants: &[MatchParticipant],
+    offset: usize,
+    context: &MatchingContext,
+) -> bool {
+    if context.reciprocal_matches != ReciprocalMatches::Allow
+        && is_reciprocal_offset(participants.len(), offset)
+    {
+        return false;
+    }
+
+    participants.iter().enumerate().all(|(i, giver)| {
+        let receiver = &participants[(i + offset) % participants.len()];
+        context.constraints.is_allowed(giver.id, receiver.id)
+    })
+}
+
+fn get_matches_for_offset(
+    participants: &[MatchParticipant],
+    offset: usize,
+    context: &MatchingContext,
+) -> MatchingResult {
+    let mut result = MatchingResult::default();
+    let number_of_participants = participants.len();
+    let skip_reciprocal_matches = context.reciprocal_matches == ReciprocalMatches::Forbid
+        && is_reciprocal_offset(number_of_participants, offset);
+
+    for (i, giver) in participants.iter().enumerate() {
+        let receiver_index = (i + offset) % number_of_participants;
+        let receiver = &participants[receiver_index];
+
+        // Of every reciprocal pair only the match of the first participant is kept
+        if !context.constraints.is_allowed(giver.id, receiver.id)
+            || (skip_reciprocal_matches && receiver_index < i)
+        {
+            result.unmatched_givers.push(giver.clone());
+            result.unmatched_receivers.push(receiver.clone());
+            continue;
+        }
+
+        let score = context.scorer.score(giver, receiver);
+        result
+            .matches
+            .push(create_match(giver.clone(), receiver.clone(), score));
+    }
+
+    result
+}
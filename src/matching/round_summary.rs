@@ -0,0 +1,64 @@
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::r#match::Match;
+
+use std::collections::BTreeMap;
+
+/// The strategy, seed and number of tries of the round, `None` for rounds created before they
+/// were recorded
+pub fn get_settings_info(matching_round: &MatchingRound) -> Option<String> {
+    let (Some(seed), Some(strategy)) = (matching_round.seed, matching_round.strategy) else {
+        return None;
+    };
+
+    let tries_info = match matching_round.number_of_tries {
+        Some(number_of_tries) => format!(", tries: {number_of_tries}"),
+        None => String::new(),
+    };
+
+    Some(format!("Strategy: {strategy:?}, seed: {seed}{tries_info}"))
+}
+
+/// Whether the giver and the receiver of the match are in the same group
+pub fn get_switch_info(group_match: &Match) -> String {
+    let giver_group = group_match.giver.group_id;
+    let receiver_group = group_match.receiver.group_id;
+
+    match giver_group != receiver_group {
+        true => format!("Cross-Team Match between {giver_group} and {receiver_group}"),
+        false => format!("In-Team Match in group {giver_group}"),
+    }
+}
+
+/// The lowest, average and highest score of the matches of a round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreRange {
+    pub min: i64,
+    pub mean: i64,
+    pub max: i64,
+}
+
+/// `None` for a round without matches
+pub fn get_score_range(matches: &[Match]) -> Option<ScoreRange> {
+    let scores: Vec<i64> = matches.iter().map(|m| m.score).collect();
+
+    let (Some(min), Some(max)) = (scores.iter().min(), scores.iter().max()) else {
+        return None;
+    };
+    let mean = scores.iter().sum::<i64>() / scores.len() as i64;
+
+    Some(ScoreRange {
+        min: *min,
+        mean,
+        max: *max,
+    })
+}
+
+/// How many matches have each score, lowest score first
+pub fn get_score_counts(matches: &[Match]) -> BTreeMap<i64, usize> {
+    matches
+        .iter()
+        .fold(BTreeMap::<i64, usize>::new(), |mut acc, m| {
+            *acc.entry(m.score).or_insert(0) += 1;
+            acc
+        })
+}
@@ -0,0 +1,189 @@
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participant::MatchParticipant;
+use crate::MAX_SCORE;
+
+use super::last_match_map::{add_reverse_matches, get_last_match_map, get_match_count_map};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use time::Date;
+
+/// How the score of a pair grows with the days since its last match
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum RecencyCurve {
+    /// The days since the last match times the factor
+    Linear { factor: f64 },
+    /// The factor times the natural logarithm of one plus the days since the last match
+    Logarithmic { factor: f64 },
+    /// Approaches the max score, reaching half of it after the half-life
+    Saturating { max_score: f64, half_life_days: f64 },
+}
+
+impl Default for RecencyCurve {
+    fn default() -> Self {
+        RecencyCurve::Linear { factor: 1.0 }
+    }
+}
+
+/// The scoring model read from `scoring.json`. The defaults score a pair with the days since
+/// its last match, or `MAX_SCORE` if it has never been matched.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ScoringConfig {
+    /// The score of a pair that has never been matched
+    pub never_matched_score: i64,
+    pub recency_curve: RecencyCurve,
+    /// Added to the score of pairs from different groups
+    pub cross_team_bonus: i64,
+    /// Added to the score of pairs with the same gender
+    pub same_gender_score: i64,
+    /// Added to the score of pairs with different genders
+    pub different_gender_score: i64,
+    /// Subtracted from the score for every past match of the pair
+    pub past_match_penalty: i64,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        ScoringConfig {
+            never_matched_score: MAX_SCORE,
+            recency_curve: RecencyCurve::default(),
+            cross_team_bonus: 0,
+            same_gender_score: 0,
+            different_gender_score: 0,
+            past_match_penalty: 0,
+        }
+    }
+}
+
+/// Scores pairs with the scoring config based on the history of past matching rounds
+#[derive(Debug, Clone)]
+pub struct Scorer {
+    pub config: ScoringConfig,
+    pub last_match_map: HashMap<(u32, u32), i64>,
+    pub match_count_map: HashMap<(u32, u32), u32>,
+}
+
+impl Scorer {
+    /// Scores the pairs for a round on the as-of date
+    pub fn new(
+        config: &ScoringConfig,
+        past_matching_rounds: &[MatchingRound],
+        as_of: Date,
+    ) -> Scorer {
+        Scorer {
+            config: config.clone(),
+            last_match_map: get_last_match_map(past_matching_rounds, as_of),
+            match_count_map: get_match_count_map(past_matching_rounds),
+        }
+    }
+
+    /// Counts every past match for the reverse direction of the pair as well
+    pub fn with_reverse_matches(mut self) -> Scorer {
+        self.last_match_map = add_reverse_matches(&self.last_match_map);
+
+        let mut symmetric_match_count_map = self.match_count_map.clone();
+
+        for ((giver_id, receiver_id), count) in &self.match_count_map {
+            *symmetric_match_count_map
+                .entry((*receiver_id, *giver_id))
+                .or_insert(0) += count;
+        }
+
+        self.match_count_map = symmetric_match_count_map;
+        self
+    }
+
+    pub fn has_matched(&self, giver_id: u32, receiver_id: u32) -> bool {
+        self.last_match_map.contains_key(&(giver_id, receiver_id))
+    }
+
+    pub fn score(&self, giver: &MatchParticipant, receiver: &MatchParticipant) -> i64 {
+        let config = &self.config;
+
+        let mut score = match self.last_match_map.get(&(giver.id, receiver.id)) {
+            Some(days) => recency_score(&config.recency_curve, *days),
+            None => config.never_matched_score,
+        };
+
+        if giver.group_id != receiver.group_id {
+            score += config.cross_team_bonus;
+        }
+
+        score += match giver.gender == receiver.gender {
+            true => config.same_gender_score,
+            false => config.different_gender_score,
+        };
+
+        let number_of_matches = self
+            .match_count_map
+            .get(&(giver.id, receiver.id))
+            .copied()
+            .unwrap_or(0);
+
+        score - config.past_match_penalty * number_of_matches as i64
+    }
+}
+
+fn recency_score(recency_curve: &RecencyCurve, days: i64) -> i64 {
+    let days = days as f64;
+
+    let score = match recency_curve {
+        RecencyCurve::Linear { factor } => factor * days,
+        RecencyCurve::Logarithmic { factor } => factor * days.max(0.0).ln_1p(),
+        RecencyCurve::Saturating {
+            max_score,
+            half_life_days,
+        } => max_score * (1.0 - 0.5f64.powf(days / half_life_days)),
+    };
+
+    score.round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::participant::Gender;
+    use crate::test_fixtures::match_participant;
+
+    fn scorer(config: ScoringConfig) -> Scorer {
+        Scorer {
+            config,
+            last_match_map: HashMap::from([((1, 2), 40)]),
+            match_count_map: HashMap::from([((1, 2), 3)]),
+        }
+    }
+
+    #[test]
+    fn test_default_config_scores_days_since_last_match() {
+        let scorer = scorer(ScoringConfig::default());
+        let first = match_participant(1, 1);
+        let second = MatchParticipant {
+            gender: Gender::Male,
+            ..match_participant(2, 1)
+        };
+
+        assert_eq!(scorer.score(&first, &second), 40);
+        assert_eq!(scorer.score(&second, &first), MAX_SCORE);
+    }
+
+    #[test]
+    fn test_configured_weights_are_added() {
+        let scorer = scorer(ScoringConfig {
+            never_matched_score: 500,
+            recency_curve: RecencyCurve::Saturating {
+                max_score: 200.0,
+                half_life_days: 40.0,
+            },
+            cross_team_bonus: 30,
+            same_gender_score: -20,
+            different_gender_score: 10,
+            past_match_penalty: 5,
+        });
+        let first = match_participant(1, 1);
+        let second = match_participant(2, 2);
+
+        assert_eq!(scorer.score(&first, &second), 100 + 30 - 20 - 15);
+        assert_eq!(scorer.score(&second, &first), 500 + 30 - 20);
+    }
+}
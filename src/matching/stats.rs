@@ -0,0 +1,298 @@
+use super::last_match_map::get_match_count_map;
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participants_file::ParticipantsFile;
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// How well the pairs within the groups have been covered by the past rounds
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub groups: Vec<GroupStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupStats {
+    pub group_id: i32,
+    /// The pairs of active participants of the group that aren't forbidden
+    pub possible_pairs: usize,
+    pub covered_pairs: usize,
+    pub coverage_percent: f64,
+    /// The number of rounds the group took part in
+    pub rounds: usize,
+    /// Based on the pairs covered per round so far, `None` if no pair has been covered yet
+    pub rounds_until_full_coverage: Option<usize>,
+    pub participants: Vec<ParticipantStats>,
+    pub never_matched: Vec<Pair>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParticipantStats {
+    pub id: u32,
+    pub name: String,
+    pub possible_receivers: usize,
+    pub covered_receivers: usize,
+    pub coverage_percent: f64,
+    /// How often the participant gave feedback, in any group
+    pub times_giver: u32,
+    /// How often the participant received feedback, in any group
+    pub times_receiver: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Pair {
+    pub giver_id: u32,
+    pub giver_name: String,
+    pub receiver_id: u32,
+    pub receiver_name: String,
+}
+
+pub fn get_stats(
+    participants_file: &ParticipantsFile,
+    past_matching_rounds: &[MatchingRound],
+) -> Stats {
+    let match_count_map = get_match_count_map(past_matching_rounds);
+    let constraints = &participants_file.constraints;
+
+    let mut times_giver: HashMap<u32, u32> = HashMap::new();
+    let mut times_receiver: HashMap<u32, u32> = HashMap::new();
+    for ((giver_id, receiver_id), count) in &match_count_map {
+        *times_giver.entry(*giver_id).or_insert(0) += count;
+        *times_receiver.entry(*receiver_id).or_insert(0) += count;
+    }
+
+    let groups = participants_file
+        .groups
+        .iter()
+        .map(|group| {
+            let participants = &group.participants;
+            let mut never_matched = vec![];
+
+            let participant_stats: Vec<ParticipantStats> = participants
+                .iter()
+                .map(|giver| {
+                    let receivers = participants.iter().filter(|receiver| {
+                        receiver.id != giver.id && !constraints.is_forbidden(giver.id, receiver.id)
+                    });
+
+                    let mut possible_receivers = 0;
+                    let mut covered_receivers = 0;
+
+                    for receiver in receivers {
+                        possible_receivers += 1;
+
+                        match match_count_map.contains_key(&(giver.id, receiver.id)) {
+                            true => covered_receivers += 1,
+                            false => never_matched.push(Pair {
+                                giver_id: giver.id,
+                                giver_name: giver.full_name(),
+                                receiver_id: receiver.id,
+                                receiver_name: receiver.full_name(),
+                            }),
+                        }
+                    }
+
+                    ParticipantStats {
+                        id: giver.id,
+                        name: giver.full_name(),
+                        possible_receivers,
+                        covered_receivers,
+                        coverage_percent: get_percent(covered_receivers, possible_receivers),
+                        times_giver: times_giver.get(&giver.id).copied().unwrap_or(0),
+                        times_receiver: times_receiver.get(&giver.id).copied().unwrap_or(0),
+                    }
+                })
+                .collect();
+
+            let possible_pairs = participant_stats.iter().map(|p| p.possible_receivers).sum();
+            let covered_pairs = participant_stats.iter().map(|p| p.covered_receivers).sum();
+            let rounds = past_matching_rounds
+                .iter()
+                .filter(|r| r.matches.iter().any(|m| m.giver.group_id == group.id))
+                .count();
+
+            GroupStats {
+                group_id: group.id,
+                possible_pairs,
+                covered_pairs,
+                coverage_percent: get_percent(covered_pairs, possible_pairs),
+                rounds,
+                rounds_until_full_coverage: get_rounds_until_full_coverage(
+                    possible_pairs - covered_pairs,
+                    covered_pairs,
+                    rounds,
+                ),
+                participants: participant_stats,
+                never_matched,
+            }
+        })
+        .collect();
+
+    Stats { groups }
+}
+
+/// Groups and participants without possible receivers count as fully covered
+fn get_percent(part: usize, total: usize) -> f64 {
+    match total {
+        0 => 100.0,
+        _ => part as f64 * 100.0 / total as f64,
+    }
+}
+
+/// The remaining pairs divided by the average number of pairs covered per round, rounded up
+fn get_rounds_until_full_coverage(
+    remaining_pairs: usize,
+    covered_pairs: usize,
+    rounds: usize,
+) -> Option<usize> {
+    if remaining_pairs == 0 {
+        return Some(0);
+    }
+
+    if covered_pairs == 0 {
+        return None;
+    }
+
+    Some((remaining_pairs * rounds).div_ceil(covered_pairs))
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for group in &self.groups {
+            writeln!(
+                f,
+                "Group {}: {:.1} % of the pairs covered ({} of {}) in {} rounds",
+                group.group_id,
+                group.coverage_percent,
+                group.covered_pairs,
+                group.possible_pairs,
+                group.rounds
+            )?;
+
+            match group.rounds_until_full_coverage {
+                Some(0) => writeln!(f, "Every pair has been matched")?,
+                Some(rounds) => writeln!(
+                    f,
+                    "Full coverage in about {rounds} more rounds at the current pace"
+                )?,
+                None => writeln!(f, "No pair has been matched yet")?,
+            }
+
+            writeln!(f)?;
+            for p in &group.participants {
+                writeln!(
+                    f,
+                    "{:>6} {:<24} {:>5.1} % ({} of {} receivers), {}x giver, {}x receiver",
+                    p.id,
+                    p.name,
+                    p.coverage_percent,
+                    p.covered_receivers,
+                    p.possible_receivers,
+                    p.times_giver,
+                    p.times_receiver
+                )?;
+            }
+
+            if !group.never_matched.is_empty() {
+                writeln!(f, "\nNever matched:")?;
+                for pair in &group.never_matched {
+                    writeln!(f, "    {} => {}", pair.giver_name, pair.receiver_name)?;
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::participants_file::{Constraints, PairConstraint, ParticipantsGroup};
+    use crate::test_fixtures::{matching_round, pair_match, participant};
+    use time::macros::date;
+
+    #[test]
+    fn test_stats() {
+        let participants_file = ParticipantsFile {
+            groups: vec![ParticipantsGroup {
+                participants: vec![participant(1), participant(2), participant(3)],
+                ..ParticipantsGroup::new(1)
+            }],
+            constraints: Constraints {
+                forbidden_pairs: vec![PairConstraint {
+                    giver_id: 1,
+                    receiver_id: 3,
+                }],
+                required_pairs: vec![],
+            },
+        };
+        let date = date!(2024 - 03 - 07);
+        let rounds = vec![
+            matching_round(
+                1,
+                date,
+                vec![
+                    pair_match(1, 2, 0),
+                    pair_match(2, 3, 0),
+                    pair_match(3, 1, 0),
+                ],
+            ),
+            matching_round(2, date, vec![pair_match(1, 2, 0)]),
+        ];
+
+        let stats = get_stats(&participants_file, &rounds);
+        let group = &stats.groups[0];
+
+        // The forbidden pair isn't possible, 3 of the remaining 5 pairs have been matched
+        assert_eq!(group.possible_pairs, 5);
+        assert_eq!(group.covered_pairs, 3);
+        assert_eq!(group.coverage_percent, 60.0);
+        assert_eq!(group.rounds, 2);
+        assert_eq!(group.rounds_until_full_coverage, Some(2));
+
+        let never_matched: Vec<(u32, u32)> = group
+            .never_matched
+            .iter()
+            .map(|p| (p.giver_id, p.receiver_id))
+            .collect();
+        assert_eq!(never_matched, vec![(2, 1), (3, 2)]);
+
+        let counts: Vec<(usize, usize, u32, u32)> = group
+            .participants
+            .iter()
+            .map(|p| {
+                (
+                    p.possible_receivers,
+                    p.covered_receivers,
+                    p.times_giver,
+                    p.times_receiver,
+                )
+            })
+            .collect();
+        assert_eq!(counts, vec![(1, 1, 2, 1), (2, 1, 1, 2), (2, 1, 1, 1)]);
+
+        assert!(stats
+            .to_string()
+            .contains("Never matched:\n    First2 Last2 => First1 Last1\n"));
+    }
+
+    #[test]
+    fn test_rounds_until_full_coverage() {
+        assert_eq!(get_rounds_until_full_coverage(0, 6, 2), Some(0));
+        assert_eq!(get_rounds_until_full_coverage(6, 0, 0), None);
+        // 3 pairs per round, so 2 rounds for the remaining 4 pairs
+        assert_eq!(get_rounds_until_full_coverage(4, 6, 2), Some(2));
+        assert_eq!(get_rounds_until_full_coverage(3, 6, 2), Some(1));
+    }
+}
@@ -1,25 +1,33 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use time::Date;
 
-use crate::matching::last_match_map::{get_days_since_last_match, get_last_match_map};
-use crate::structs::matching_round::MatchingRound;
+use crate::matching::feedback_date::{calculate_feedback_date, DEFAULT_FEEDBACK_WEEKDAY};
+use crate::matching::scoring::{Scorer, ScoringConfig};
+use crate::structs::calendar::HolidayCalendar;
+use crate::structs::matching_round::{GroupFeedbackDate, MatchingRound};
 use crate::structs::participant::MatchParticipant;
 use crate::structs::participants_file::ParticipantsFile;
 use crate::structs::r#match::Match;
 
-pub fn calculate_scores(past_matching_rounds: &Vec<MatchingRound>) -> Vec<MatchingRound> {
-    let mut new_matching_rounds = past_matching_rounds.clone();
+/// Scores the matches of every round against the rounds before it. The days since the past
+/// matches are counted until the as-of date, or until the date of each round if it's omitted, so
+/// the scores are the ones the round would have got when it was created.
+pub fn calculate_scores(
+    past_matching_rounds: &[MatchingRound],
+    scoring_config: &ScoringConfig,
+    as_of: Option<Date>,
+) -> Vec<MatchingRound> {
+    let mut new_matching_rounds = past_matching_rounds.to_vec();
     let mut passed_matching_rounds = Vec::<MatchingRound>::new();
 
     for matching_round in &mut new_matching_rounds {
-        let last_match_map = get_last_match_map(&passed_matching_rounds);
+        let scorer = Scorer::new(
+            scoring_config,
+            &passed_matching_rounds,
+            as_of.unwrap_or(matching_round.date),
+        );
         for group_match in &mut matching_round.matches {
-            let score = get_days_since_last_match(
-                &last_match_map,
-                group_match.giver.id,
-                group_match.receiver.id,
-            );
-
-            group_match.score = score;
+            group_match.score = scorer.score(&group_match.giver, &group_match.receiver);
         }
 
         passed_matching_rounds.push(matching_round.clone())
@@ -29,7 +37,7 @@ pub fn calculate_scores(past_matching_rounds: &Vec<MatchingRound>) -> Vec<Matchi
 }
 
 pub fn update_matching_rounds_with_group_ids(
-    past_matching_rounds: &Vec<MatchingRound>,
+    past_matching_rounds: &[MatchingRound],
     participants_file: &ParticipantsFile,
 ) -> Vec<MatchingRound> {
     let participant_group_map = create_participant_group_map(participants_file);
@@ -68,8 +76,52 @@ pub fn update_matching_rounds_with_group_ids(
         .collect()
 }
 
+/// Records the interval and the feedback dates of rounds created before they were stored. The
+/// dates are the ones the messages suggested back then, on Thursday without blocked days.
+pub fn update_matching_rounds_with_feedback_dates(
+    past_matching_rounds: &[MatchingRound],
+    intervall_weeks: i32,
+) -> Vec<MatchingRound> {
+    past_matching_rounds
+        .iter()
+        .map(|round| {
+            let intervall_weeks = round.intervall_weeks.unwrap_or(intervall_weeks);
+
+            let feedback_dates = match round.feedback_dates.is_empty() {
+                false => round.feedback_dates.clone(),
+                true => {
+                    let feedback_date = calculate_feedback_date(
+                        round.date,
+                        intervall_weeks,
+                        DEFAULT_FEEDBACK_WEEKDAY,
+                        &HolidayCalendar::default(),
+                    );
+
+                    round
+                        .matches
+                        .iter()
+                        .map(|m| m.giver.group_id)
+                        .collect::<BTreeSet<i32>>()
+                        .into_iter()
+                        .map(|group_id| GroupFeedbackDate {
+                            group_id,
+                            date: feedback_date,
+                        })
+                        .collect()
+                }
+            };
+
+            MatchingRound {
+                intervall_weeks: Some(intervall_weeks),
+                feedback_dates,
+                ..round.clone()
+            }
+        })
+        .collect()
+}
+
 fn get_group_id_for_participant(map: HashMap<u32, i32>, participant_id: &u32) -> i32 {
-    *map.get(&participant_id)
+    *map.get(participant_id)
         .expect("Could not find group for participant")
 }
 
@@ -88,3 +140,29 @@ fn create_participant_group_map(participants_file: &ParticipantsFile) -> HashMap
             acc
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{matching_round, pair_match};
+    use crate::MAX_SCORE;
+    use time::macros::date;
+
+    #[test]
+    fn test_calculate_scores() {
+        let rounds = vec![
+            matching_round(1, date!(2024 - 03 - 07), vec![pair_match(1, 2, 0)]),
+            matching_round(2, date!(2024 - 03 - 21), vec![pair_match(1, 2, 0)]),
+        ];
+        let scores = |as_of| -> Vec<i64> {
+            calculate_scores(&rounds, &ScoringConfig::default(), as_of)
+                .iter()
+                .map(|r| r.matches[0].score)
+                .collect()
+        };
+
+        // The second round is scored with the 14 days since the first one
+        assert_eq!(scores(None), vec![MAX_SCORE, 14]);
+        assert_eq!(scores(Some(date!(2024 - 04 - 04))), vec![MAX_SCORE, 28]);
+    }
+}
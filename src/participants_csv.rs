@@ -0,0 +1,524 @@
+use crate::error::Error;
+use crate::file_io::write_atomically;
+use crate::storage::Storage;
+use crate::structs::locale::Locale;
+use crate::structs::participant::{Gender, Participant};
+use crate::structs::participants_file::{ParticipantsFile, ParticipantsGroup};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+
+const COLUMNS: [&str; 9] = [
+    "id",
+    "first_name",
+    "last_name",
+    "gender",
+    "group",
+    "excluded",
+    "email",
+    "locale",
+    "webhook_url",
+];
+
+/// A row of the roster, the id is optional so new people can be added without one
+#[derive(Debug, Clone)]
+pub struct CsvParticipant {
+    pub id: Option<u32>,
+    pub first_name: String,
+    pub last_name: String,
+    pub gender: Gender,
+    pub group_id: i32,
+    pub excluded: bool,
+    pub email: Option<String>,
+    pub locale: Option<Locale>,
+    pub webhook_url: Option<String>,
+}
+
+/// The changes an import makes to the participants
+#[derive(Debug, Default)]
+pub struct RosterDiff {
+    pub added: Vec<(String, i32)>,
+    pub removed: Vec<(String, i32)>,
+    pub moved: Vec<(String, i32, i32)>,
+    pub excluded: Vec<String>,
+    pub included: Vec<String>,
+    /// The participants whose details changed, with the changed columns
+    pub updated: Vec<(String, Vec<&'static str>)>,
+}
+
+impl RosterDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.excluded.is_empty()
+            && self.included.is_empty()
+            && self.updated.is_empty()
+    }
+}
+
+impl Display for RosterDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No participants are added, removed, moved or changed");
+        }
+
+        for (name, group_id) in &self.added {
+            writeln!(f, "+ {name} (group {group_id})")?;
+        }
+        for (name, group_id) in &self.removed {
+            writeln!(f, "- {name} (group {group_id})")?;
+        }
+        for (name, from, to) in &self.moved {
+            writeln!(f, "~ {name} (group {from} -> {to})")?;
+        }
+        for name in &self.excluded {
+            writeln!(f, "~ {name} (excluded)")?;
+        }
+        for name in &self.included {
+            writeln!(f, "~ {name} (included)")?;
+        }
+        for (name, columns) in &self.updated {
+            writeln!(f, "~ {name} ({} changed)", columns.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes one row per participant, groups in order and the active participants of a group
+/// before the excluded ones
+pub fn write_participants_csv(
+    file_path: &str,
+    participants_file: &ParticipantsFile,
+) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let csv_error = |e: csv::Error| Error::csv(file_path, e);
+
+    writer.write_record(COLUMNS).map_err(csv_error)?;
+
+    for group in &participants_file.groups {
+        let all_participants = [
+            (false, &group.participants),
+            (true, &group.excluded_participants),
+        ];
+
+        for (excluded, participants) in all_participants {
+            for p in participants {
+                writer
+                    .write_record([
+                        p.id.to_string(),
+                        p.first_name.clone(),
+                        p.last_name.clone(),
+                        format!("{:?}", p.gender),
+                        group.id.to_string(),
+                        excluded.to_string(),
+                        p.email.clone().unwrap_or_default(),
+                        p.locale.map(|l| l.code().to_string()).unwrap_or_default(),
+                        p.webhook_url.clone().unwrap_or_default(),
+                    ])
+                    .map_err(csv_error)?;
+            }
+        }
+    }
+
+    let contents = writer
+        .into_inner()
+        .map_err(|e| Error::csv(file_path, e.into_error().into()))?;
+
+    write_atomically(file_path, &String::from_utf8_lossy(&contents))
+}
+
+/// Reads the roster. Column names are matched case-insensitively with spaces as underscores,
+/// so "First Name" works as well, and unknown columns are ignored.
+pub fn read_participants_csv(file_path: &str) -> Result<Vec<CsvParticipant>, Error> {
+    let mut reader = csv::Reader::from_path(file_path).map_err(|e| Error::csv(file_path, e))?;
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| Error::csv(file_path, e))?
+        .iter()
+        .map(|h| h.trim().to_lowercase().replace(' ', "_"))
+        .collect();
+
+    let unknown_columns: Vec<&String> = headers
+        .iter()
+        .filter(|h| !COLUMNS.contains(&h.as_str()))
+        .collect();
+    if !unknown_columns.is_empty() {
+        println!("Ignoring the columns {unknown_columns:?}");
+    }
+
+    let mut rows = vec![];
+    let mut errors = vec![];
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| Error::csv(file_path, e))?;
+        let values: HashMap<&str, &str> = headers
+            .iter()
+            .map(|h| h.as_str())
+            .zip(record.iter().map(|v| v.trim()))
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+
+        // The header is the first line
+        match parse_row(&values) {
+            Ok(row) => rows.push(row),
+            Err(error) => errors.push(format!("Line {}: {error}", index + 2)),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(rows),
+        false => Err(Error::Validation {
+            path: file_path.to_string(),
+            errors,
+        }),
+    }
+}
+
+fn parse_row(values: &HashMap<&str, &str>) -> Result<CsvParticipant, String> {
+    let required = |column: &str| {
+        values
+            .get(column)
+            .map(|v| v.to_string())
+            .ok_or_else(|| format!("The {column} is missing"))
+    };
+    let optional = |column: &str| values.get(column).map(|v| v.to_string());
+
+    Ok(CsvParticipant {
+        id: optional("id")
+            .map(|id| {
+                id.parse()
+                    .map_err(|_| format!("The id {id} isn't a number"))
+            })
+            .transpose()?,
+        first_name: required("first_name")?,
+        last_name: required("last_name")?,
+        gender: match required("gender")?.to_lowercase().as_str() {
+            "male" => Gender::Male,
+            "female" => Gender::Female,
+            "other" => Gender::Other,
+            gender => return Err(format!("The gender {gender} is unknown")),
+        },
+        group_id: required("group").and_then(|group| {
+            group
+                .parse()
+                .map_err(|_| format!("The group {group} isn't a number"))
+        })?,
+        excluded: match optional("excluded")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "false" | "no" | "0" => false,
+            "true" | "yes" | "1" | "x" => true,
+            excluded => return Err(format!("The excluded flag {excluded} isn't a boolean")),
+        },
+        email: optional("email"),
+        locale: match optional("locale").map(|l| l.to_lowercase()).as_deref() {
+            None => None,
+            Some("de") => Some(Locale::De),
+            Some("en") => Some(Locale::En),
+            Some(locale) => return Err(format!("The locale {locale} is unknown")),
+        },
+        webhook_url: optional("webhook_url"),
+    })
+}
+
+/// Replaces the participants with the roster. Rows without a known id keep the id of the
+/// participant with the same email address or name, so importing the same roster again doesn't
+/// create duplicates. The settings of existing groups, the pronouns and the constraints between
+/// remaining participants are kept.
+pub fn import_roster(
+    participants_file: &ParticipantsFile,
+    rows: &[CsvParticipant],
+    used_ids: impl IntoIterator<Item = u32>,
+) -> Result<(ParticipantsFile, RosterDiff), Error> {
+    let existing: Vec<(&Participant, i32, bool)> = participants_file
+        .groups
+        .iter()
+        .flat_map(|g| {
+            let active = g.participants.iter().map(move |p| (p, g.id, false));
+            let excluded = g.excluded_participants.iter().map(move |p| (p, g.id, true));
+            active.chain(excluded)
+        })
+        .collect();
+
+    let find_existing = |row: &CsvParticipant| {
+        let by_id = row
+            .id
+            .and_then(|id| existing.iter().find(|(p, ..)| p.id == id));
+        let by_email = || {
+            row.email.as_ref().and_then(|email| {
+                existing.iter().find(|(p, ..)| {
+                    p.email
+                        .as_ref()
+                        .is_some_and(|e| e.eq_ignore_ascii_case(email))
+                })
+            })
+        };
+        let by_name = || {
+            let name = format!("{} {}", row.first_name, row.last_name).to_lowercase();
+            existing
+                .iter()
+                .find(|(p, ..)| p.full_name().to_lowercase() == name)
+        };
+
+        by_id.or_else(by_email).or_else(by_name)
+    };
+
+    let mut next_id = participants_file
+        .next_participant_id(used_ids.into_iter().chain(rows.iter().filter_map(|r| r.id)));
+    let mut assigned_ids = HashSet::new();
+    let mut errors = vec![];
+    let mut diff = RosterDiff::default();
+    let mut new_file = ParticipantsFile {
+        groups: vec![],
+        constraints: participants_file.constraints.clone(),
+    };
+
+    for row in rows {
+        let matched = find_existing(row);
+        let name = format!("{} {}", row.first_name, row.last_name);
+
+        let (id, pronouns) = match (matched, row.id) {
+            (Some((p, group_id, excluded)), _) => {
+                if *group_id != row.group_id {
+                    diff.moved.push((name.clone(), *group_id, row.group_id));
+                }
+                match (excluded, row.excluded) {
+                    (false, true) => diff.excluded.push(name.clone()),
+                    (true, false) => diff.included.push(name.clone()),
+                    _ => {}
+                }
+                let changed_columns = get_changed_columns(p, row);
+                if !changed_columns.is_empty() {
+                    diff.updated.push((name.clone(), changed_columns));
+                }
+                (p.id, p.pronouns.clone())
+            }
+            (None, Some(id)) => {
+                diff.added.push((name.clone(), row.group_id));
+                (id, None)
+            }
+            (None, None) => {
+                diff.added.push((name.clone(), row.group_id));
+                next_id += 1;
+                (next_id - 1, None)
+            }
+        };
+
+        if !assigned_ids.insert(id) {
+            errors.push(format!("{name} has the id {id} of another row"));
+            continue;
+        }
+
+        if !new_file.groups.iter().any(|g| g.id == row.group_id) {
+            let group = participants_file
+                .groups
+                .iter()
+                .find(|g| g.id == row.group_id)
+                .map(|g| ParticipantsGroup {
+                    participants: vec![],
+                    excluded_participants: vec![],
+                    ..g.clone()
+                })
+                .unwrap_or_else(|| ParticipantsGroup::new(row.group_id));
+            new_file.groups.push(group);
+        }
+
+        new_file.add_participant(
+            row.group_id,
+            Participant {
+                id,
+                first_name: row.first_name.clone(),
+                last_name: row.last_name.clone(),
+                gender: row.gender.clone(),
+                pronouns,
+                locale: row.locale,
+                email: row.email.clone(),
+                webhook_url: row.webhook_url.clone(),
+            },
+            row.excluded,
+        );
+    }
+
+    if !errors.is_empty() {
+        return Err(Error::Validation {
+            path: "the imported roster".to_string(),
+            errors,
+        });
+    }
+
+    for (participant, group_id, _) in &existing {
+        if !assigned_ids.contains(&participant.id) {
+            diff.removed.push((participant.full_name(), *group_id));
+        }
+    }
+
+    let constraints = &mut new_file.constraints;
+    for pairs in [
+        &mut constraints.forbidden_pairs,
+        &mut constraints.required_pairs,
+    ] {
+        pairs.retain(|p| {
+            assigned_ids.contains(&p.giver_id) && assigned_ids.contains(&p.receiver_id)
+        });
+    }
+
+    Ok((new_file, diff))
+}
+
+fn get_changed_columns(participant: &Participant, row: &CsvParticipant) -> Vec<&'static str> {
+    [
+        ("first_name", participant.first_name != row.first_name),
+        ("last_name", participant.last_name != row.last_name),
+        ("gender", participant.gender != row.gender),
+        ("email", participant.email != row.email),
+        ("locale", participant.locale != row.locale),
+        ("webhook_url", participant.webhook_url != row.webhook_url),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(column, _)| column)
+    .collect()
+}
+
+/// Replaces the participants of the storage with the roster of the CSV file and prints the
+/// changes. Nothing is saved with `dry_run` or if nothing changes.
+pub fn import_csv(csv_path: &str, dry_run: bool, storage: &dyn Storage) -> Result<(), Error> {
+    let participants_file = storage.read_participants()?;
+    let past_matching_rounds = storage.read_matching_rounds()?;
+    let rows = read_participants_csv(csv_path)?;
+
+    // Ids of the history aren't given to new people
+    let used_ids = past_matching_rounds
+        .iter()
+        .flat_map(|r| r.matches.iter())
+        .flat_map(|m| [m.giver.id, m.receiver.id]);
+
+    let (imported_file, diff) = import_roster(&participants_file, &rows, used_ids)?;
+    println!("{diff}");
+
+    if !dry_run && !diff.is_empty() {
+        storage.save_participants(&imported_file)?;
+        println!("Imported {} participants from {csv_path}", rows.len());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_io::ValidationMode;
+    use crate::storage::json_storage::JsonStorage;
+    use std::fs;
+
+    fn row(
+        id: Option<u32>,
+        first_name: &str,
+        group_id: i32,
+        email: Option<&str>,
+    ) -> CsvParticipant {
+        CsvParticipant {
+            id,
+            first_name: first_name.to_string(),
+            last_name: "Last".to_string(),
+            gender: Gender::Female,
+            group_id,
+            excluded: false,
+            email: email.map(|e| e.to_string()),
+            locale: None,
+            webhook_url: None,
+        }
+    }
+
+    #[test]
+    fn test_import_reconciles_ids() {
+        let (participants_file, _) = import_roster(
+            &ParticipantsFile {
+                groups: vec![],
+                constraints: Default::default(),
+            },
+            &[
+                row(Some(1), "Anna", 1, None),
+                row(None, "Ben", 1, Some("ben@example.com")),
+                row(None, "Cara", 2, None),
+            ],
+            [5],
+        )
+        .unwrap();
+
+        let ids: Vec<u32> = participants_file.all_participants().map(|p| p.id).collect();
+        assert_eq!(ids, vec![1, 6, 7]);
+
+        // The same people without ids, Ben with a new name and Cara in another group
+        let (reimported_file, diff) = import_roster(
+            &participants_file,
+            &[
+                row(None, "Anna", 1, None),
+                row(None, "Benjamin", 1, Some("BEN@example.com")),
+                row(None, "Cara", 1, None),
+                row(None, "Dan", 2, None),
+            ],
+            [5],
+        )
+        .unwrap();
+
+        let ids: Vec<u32> = reimported_file.all_participants().map(|p| p.id).collect();
+        assert_eq!(ids, vec![1, 6, 7, 8]);
+        assert_eq!(diff.added, vec![("Dan Last".to_string(), 2)]);
+        assert_eq!(diff.moved, vec![("Cara Last".to_string(), 2, 1)]);
+        assert_eq!(
+            diff.updated,
+            vec![("Benjamin Last".to_string(), vec!["first_name", "email"])]
+        );
+        assert!(diff.removed.is_empty());
+
+        assert!(import_roster(
+            &reimported_file,
+            &[row(Some(1), "Anna", 1, None), row(None, "Anna", 1, None)],
+            []
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_reimport_with_changed_email_is_saved() {
+        let directory =
+            std::env::temp_dir().join(format!("feedback-matching-import-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let data_path = directory.to_string_lossy().to_string();
+        let csv_path = format!("{data_path}/roster.csv");
+        fs::write(format!("{data_path}/matches.json"), "[]").unwrap();
+
+        let storage = JsonStorage::new(&data_path, ValidationMode::Strict);
+        storage
+            .save_participants(&ParticipantsFile {
+                groups: vec![],
+                constraints: Default::default(),
+            })
+            .unwrap();
+        let email = |storage: &JsonStorage| {
+            storage.read_participants().unwrap().groups[0].participants[0]
+                .email
+                .clone()
+        };
+
+        let roster = "id,first_name,last_name,gender,group,email\n1,Anna,Alt,Female,1,";
+        fs::write(&csv_path, format!("{roster}anna@example.com\n")).unwrap();
+        import_csv(&csv_path, false, &storage).unwrap();
+
+        fs::write(&csv_path, format!("{roster}anna.alt@example.com\n")).unwrap();
+        import_csv(&csv_path, true, &storage).unwrap();
+        assert_eq!(email(&storage).as_deref(), Some("anna@example.com"));
+
+        import_csv(&csv_path, false, &storage).unwrap();
+        assert_eq!(email(&storage).as_deref(), Some("anna.alt@example.com"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
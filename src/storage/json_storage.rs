@@ -0,0 +1,60 @@
+use crate::error::Error;
+use crate::file_io::{
+    read_matching_rounds, read_participants, save_matching_round, save_participants,
+    update_all_existing_rounds, ValidationMode,
+};
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participants_file::ParticipantsFile;
+
+use super::Storage;
+
+/// Keeps the data in `participants.json` and `matches.json` in the data directory
+pub struct JsonStorage {
+    data_path: String,
+    mode: ValidationMode,
+}
+
+impl JsonStorage {
+    pub fn new(data_path: &str, mode: ValidationMode) -> JsonStorage {
+        JsonStorage {
+            data_path: data_path.to_string(),
+            mode,
+        }
+    }
+
+    fn matches_file_path(&self) -> String {
+        format!("{}/matches.json", self.data_path)
+    }
+
+    fn participants_file_path(&self) -> String {
+        format!("{}/participants.json", self.data_path)
+    }
+}
+
+impl Storage for JsonStorage {
+    fn read_participants(&self) -> Result<ParticipantsFile, Error> {
+        read_participants(&self.participants_file_path(), self.mode)
+    }
+
+    fn save_participants(&self, participants_file: &ParticipantsFile) -> Result<(), Error> {
+        save_participants(&self.participants_file_path(), participants_file)
+    }
+
+    fn read_matching_rounds(&self) -> Result<Vec<MatchingRound>, Error> {
+        read_matching_rounds(&self.matches_file_path(), self.mode)
+    }
+
+    fn save_matching_round(&self, round: MatchingRound) -> Result<(), Error> {
+        save_matching_round(&self.matches_file_path(), round, self.mode)
+    }
+
+    fn delete_last_matching_round(&self) -> Result<(), Error> {
+        let mut rounds = self.read_matching_rounds()?;
+        rounds.pop();
+        self.update_all_existing_rounds(&rounds)
+    }
+
+    fn update_all_existing_rounds(&self, rounds: &[MatchingRound]) -> Result<(), Error> {
+        update_all_existing_rounds(&self.matches_file_path(), rounds)
+    }
+}
@@ -0,0 +1,95 @@
+pub mod json_storage;
+pub mod sqlite_storage;
+
+use crate::error::Error;
+use crate::file_io::{read_storage_config, ValidationMode};
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participants_file::ParticipantsFile;
+
+use json_storage::JsonStorage;
+use serde::{Deserialize, Serialize};
+use sqlite_storage::SqliteStorage;
+use std::path::Path;
+
+/// The `--data-path` prefix that selects the SQLite backend, e.g. `sqlite://data/feedback.sqlite`
+const SQLITE_SCHEME: &str = "sqlite://";
+
+/// Where the participants and the history of matching rounds are kept
+pub trait Storage {
+    fn read_participants(&self) -> Result<ParticipantsFile, Error>;
+    fn save_participants(&self, participants_file: &ParticipantsFile) -> Result<(), Error>;
+    fn read_matching_rounds(&self) -> Result<Vec<MatchingRound>, Error>;
+    fn save_matching_round(&self, round: MatchingRound) -> Result<(), Error>;
+    fn delete_last_matching_round(&self) -> Result<(), Error>;
+    /// Replaces the whole history, only meant for migrations
+    fn update_all_existing_rounds(&self, rounds: &[MatchingRound]) -> Result<(), Error>;
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum StorageBackend {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+/// The storage config read from `storage.json` in the data directory. Without it the JSON
+/// files are used.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// The database file of the SQLite backend, relative to the data directory
+    pub database_file: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            backend: StorageBackend::default(),
+            database_file: "feedback.sqlite".to_string(),
+        }
+    }
+}
+
+/// The `--data-path` of the CLI together with how strictly its files are validated
+#[derive(Debug, Clone)]
+pub struct DataSource {
+    pub data_path: String,
+    pub mode: ValidationMode,
+}
+
+impl DataSource {
+    /// Opens the storage for the data path. A `sqlite://` data path points to the database
+    /// file directly, any other data path is a directory with an optional `storage.json`.
+    pub fn open_storage(&self) -> Result<Box<dyn Storage>, Error> {
+        if let Some(database_path) = self.data_path.strip_prefix(SQLITE_SCHEME) {
+            return Ok(Box::new(SqliteStorage::open(database_path)?));
+        }
+
+        let storage_config =
+            read_storage_config(&self.config_file_path("storage.json"), self.mode)?;
+
+        Ok(match storage_config.backend {
+            StorageBackend::Json => Box::new(JsonStorage::new(&self.data_path, self.mode)),
+            StorageBackend::Sqlite => Box::new(SqliteStorage::open(
+                &self.config_file_path(&storage_config.database_file),
+            )?),
+        })
+    }
+
+    /// The directory with the config files and backups
+    pub fn directory(&self) -> String {
+        match self.data_path.strip_prefix(SQLITE_SCHEME) {
+            Some(database_path) => Path::new(database_path)
+                .parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .filter(|p| !p.is_empty())
+                .unwrap_or_else(|| ".".to_string()),
+            None => self.data_path.clone(),
+        }
+    }
+
+    pub fn config_file_path(&self, file_name: &str) -> String {
+        format!("{}/{}", self.directory(), file_name)
+    }
+}
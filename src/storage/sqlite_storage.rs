@@ -0,0 +1,622 @@
+use crate::structs::locale::Locale;
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participant::{MatchParticipant, Participant};
+use crate::structs::participants_file::{
+    Constraints, PairConstraint, ParticipantsFile, ParticipantsGroup,
+};
+use crate::structs::r#match::Match;
+
+use super::Storage;
+use crate::error::Error;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row, Transaction};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use time::Weekday;
+
+/// The schema changes of the database in order, `user_version` counts the applied ones.
+/// The position columns keep the order of the JSON files, so an import can be exported again
+/// without any difference.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE groups (
+        position INTEGER PRIMARY KEY,
+        id INTEGER NOT NULL
+    );
+    CREATE TABLE participants (
+        group_position INTEGER NOT NULL REFERENCES groups(position),
+        excluded INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        id INTEGER NOT NULL,
+        first_name TEXT NOT NULL,
+        last_name TEXT NOT NULL,
+        gender TEXT NOT NULL
+    );
+    CREATE TABLE pair_constraints (
+        kind TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        giver_id INTEGER NOT NULL,
+        receiver_id INTEGER NOT NULL
+    );
+    CREATE TABLE rounds (
+        position INTEGER PRIMARY KEY,
+        id INTEGER NOT NULL,
+        date TEXT NOT NULL,
+        seed INTEGER,
+        strategy TEXT,
+        number_of_tries INTEGER
+    );
+    CREATE TABLE matches (
+        round_position INTEGER NOT NULL REFERENCES rounds(position),
+        position INTEGER NOT NULL,
+        giver_id INTEGER NOT NULL,
+        giver_group_id INTEGER NOT NULL,
+        giver_first_name TEXT NOT NULL,
+        giver_last_name TEXT NOT NULL,
+        giver_gender TEXT NOT NULL,
+        receiver_id INTEGER NOT NULL,
+        receiver_group_id INTEGER NOT NULL,
+        receiver_first_name TEXT NOT NULL,
+        receiver_last_name TEXT NOT NULL,
+        receiver_gender TEXT NOT NULL,
+        score INTEGER NOT NULL
+    );
+",
+    "
+    ALTER TABLE participants ADD COLUMN pronouns TEXT;
+    ALTER TABLE matches ADD COLUMN giver_pronouns TEXT;
+    ALTER TABLE matches ADD COLUMN receiver_pronouns TEXT;
+",
+    "
+    ALTER TABLE groups ADD COLUMN locale TEXT;
+    ALTER TABLE participants ADD COLUMN locale TEXT;
+    ALTER TABLE matches ADD COLUMN giver_locale TEXT;
+    ALTER TABLE matches ADD COLUMN receiver_locale TEXT;
+",
+    "
+    ALTER TABLE groups ADD COLUMN feedback_weekday TEXT;
+    ALTER TABLE rounds ADD COLUMN feedback_dates TEXT;
+",
+    "
+    ALTER TABLE rounds ADD COLUMN intervall_weeks INTEGER;
+",
+    "
+    ALTER TABLE participants ADD COLUMN email TEXT;
+",
+    "
+    ALTER TABLE participants ADD COLUMN webhook_url TEXT;
+",
+];
+
+const FORBIDDEN_PAIR: &str = "forbidden";
+const REQUIRED_PAIR: &str = "required";
+
+/// Keeps the data in an embedded SQLite database, so saving a round only inserts its matches
+/// instead of rewriting the whole history
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(database_path: &str) -> Result<SqliteStorage, Error> {
+        let connection = Connection::open(database_path)?;
+
+        SqliteStorage::from_connection(connection)
+    }
+
+    fn from_connection(connection: Connection) -> Result<SqliteStorage, Error> {
+        let storage = SqliteStorage { connection };
+        storage.migrate()?;
+        Ok(storage)
+    }
+
+    fn migrate(&self) -> rusqlite::Result<()> {
+        let version: usize = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = self.connection.unchecked_transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", i + 1)?;
+            transaction.commit()?;
+        }
+
+        Ok(())
+    }
+
+    fn read_pairs(&self, kind: &str) -> rusqlite::Result<Vec<PairConstraint>> {
+        let mut statement = self.connection.prepare(
+            "SELECT giver_id, receiver_id FROM pair_constraints
+                 WHERE kind = ?1 ORDER BY position",
+        )?;
+
+        statement
+            .query_map([kind], |row| {
+                Ok(PairConstraint {
+                    giver_id: row.get(0)?,
+                    receiver_id: row.get(1)?,
+                })
+            })
+            .and_then(|rows| rows.collect())
+    }
+
+    fn read_group_participants(
+        &self,
+        group_position: usize,
+        excluded: bool,
+    ) -> rusqlite::Result<Vec<Participant>> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, first_name, last_name, gender, pronouns, locale, email, webhook_url
+                 FROM participants
+                 WHERE group_position = ?1 AND excluded = ?2 ORDER BY position",
+        )?;
+
+        statement
+            .query_map(params![group_position, excluded], |row| {
+                Ok(Participant {
+                    id: row.get(0)?,
+                    first_name: row.get(1)?,
+                    last_name: row.get(2)?,
+                    gender: from_text(row, 3)?,
+                    pronouns: from_optional_json(row, 4)?,
+                    locale: from_optional_text(row, 5)?,
+                    email: row.get(6)?,
+                    webhook_url: row.get(7)?,
+                })
+            })
+            .and_then(|rows| rows.collect())
+    }
+
+    fn read_matches(&self) -> rusqlite::Result<HashMap<usize, Vec<Match>>> {
+        let mut statement = self.connection.prepare(
+            "SELECT round_position,
+                    giver_id, giver_group_id, giver_first_name, giver_last_name, giver_gender,
+                    giver_pronouns, giver_locale,
+                    receiver_id, receiver_group_id, receiver_first_name, receiver_last_name,
+                    receiver_gender, receiver_pronouns, receiver_locale, score
+                 FROM matches ORDER BY round_position, position",
+        )?;
+
+        let rows: Vec<(usize, Match)> = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    Match {
+                        giver: read_match_participant(row, 1)?,
+                        receiver: read_match_participant(row, 8)?,
+                        score: row.get(15)?,
+                    },
+                ))
+            })
+            .and_then(|rows| rows.collect())?;
+
+        Ok(rows
+            .into_iter()
+            .fold(HashMap::new(), |mut acc, (round_position, group_match)| {
+                acc.entry(round_position)
+                    .or_insert_with(Vec::new)
+                    .push(group_match);
+                acc
+            }))
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn read_participants(&self) -> Result<ParticipantsFile, Error> {
+        let mut statement = self.connection.prepare(
+            "SELECT position, id, locale, feedback_weekday FROM groups ORDER BY position",
+        )?;
+
+        let group_rows: Vec<(usize, i32, Option<Locale>, Option<Weekday>)> = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    from_optional_text(row, 2)?,
+                    from_optional_text(row, 3)?,
+                ))
+            })
+            .and_then(|rows| rows.collect())?;
+
+        let groups = group_rows
+            .into_iter()
+            .map(|(position, id, locale, feedback_weekday)| {
+                Ok(ParticipantsGroup {
+                    id,
+                    participants: self.read_group_participants(position, false)?,
+                    excluded_participants: self.read_group_participants(position, true)?,
+                    locale,
+                    feedback_weekday,
+                })
+            })
+            .collect::<rusqlite::Result<Vec<ParticipantsGroup>>>()?;
+
+        Ok(ParticipantsFile {
+            groups,
+            constraints: Constraints {
+                forbidden_pairs: self.read_pairs(FORBIDDEN_PAIR)?,
+                required_pairs: self.read_pairs(REQUIRED_PAIR)?,
+            },
+        })
+    }
+
+    fn save_participants(&self, participants_file: &ParticipantsFile) -> Result<(), Error> {
+        let transaction = self.connection.unchecked_transaction()?;
+
+        transaction.execute_batch(
+            "DELETE FROM participants; DELETE FROM groups; DELETE FROM pair_constraints;",
+        )?;
+
+        for (group_position, group) in participants_file.groups.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO groups (position, id, locale, feedback_weekday)
+                     VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    group_position,
+                    group.id,
+                    group.locale.as_ref().map(to_text),
+                    group.feedback_weekday.as_ref().map(to_text),
+                ],
+            )?;
+
+            let all_participants = [
+                (false, &group.participants),
+                (true, &group.excluded_participants),
+            ];
+
+            for (excluded, participants) in all_participants {
+                for (position, participant) in participants.iter().enumerate() {
+                    transaction.execute(
+                        "INSERT INTO participants
+                             (group_position, excluded, position, id, first_name, last_name, gender,
+                             pronouns, locale, email, webhook_url)
+                             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        params![
+                            group_position,
+                            excluded,
+                            position,
+                            participant.id,
+                            participant.first_name,
+                            participant.last_name,
+                            to_text(&participant.gender),
+                            to_optional_json(&participant.pronouns),
+                            participant.locale.as_ref().map(to_text),
+                            participant.email,
+                            participant.webhook_url,
+                        ],
+                    )?;
+                }
+            }
+        }
+
+        let all_pairs = [
+            (
+                FORBIDDEN_PAIR,
+                &participants_file.constraints.forbidden_pairs,
+            ),
+            (REQUIRED_PAIR, &participants_file.constraints.required_pairs),
+        ];
+
+        for (kind, pairs) in all_pairs {
+            for (position, pair) in pairs.iter().enumerate() {
+                transaction.execute(
+                    "INSERT INTO pair_constraints (kind, position, giver_id, receiver_id)
+                         VALUES (?1, ?2, ?3, ?4)",
+                    params![kind, position, pair.giver_id, pair.receiver_id],
+                )?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn read_matching_rounds(&self) -> Result<Vec<MatchingRound>, Error> {
+        let mut matches = self.read_matches()?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT position, id, date, seed, strategy, number_of_tries, intervall_weeks,
+                    feedback_dates
+                 FROM rounds ORDER BY position",
+        )?;
+
+        let rounds = statement
+            .query_map([], |row| {
+                let position: usize = row.get(0)?;
+                let seed: Option<i64> = row.get(3)?;
+
+                Ok(MatchingRound {
+                    id: row.get(1)?,
+                    date: from_text(row, 2)?,
+                    matches: matches.remove(&position).unwrap_or_default(),
+                    // SQLite only knows signed integers, the bits of the seed are kept as they are
+                    seed: seed.map(|s| s as u64),
+                    strategy: from_optional_text(row, 4)?,
+                    number_of_tries: row.get(5)?,
+                    intervall_weeks: row.get(6)?,
+                    feedback_dates: from_optional_json(row, 7)?.unwrap_or_default(),
+                })
+            })
+            .and_then(|rows| rows.collect())?;
+
+        Ok(rounds)
+    }
+
+    fn save_matching_round(&self, round: MatchingRound) -> Result<(), Error> {
+        let transaction = self.connection.unchecked_transaction()?;
+        insert_matching_round(&transaction, &round)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn delete_last_matching_round(&self) -> Result<(), Error> {
+        let transaction = self.connection.unchecked_transaction()?;
+
+        transaction.execute_batch(
+            "DELETE FROM matches WHERE round_position = (SELECT MAX(position) FROM rounds);
+                 DELETE FROM rounds WHERE position = (SELECT MAX(position) FROM rounds);",
+        )?;
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn update_all_existing_rounds(&self, rounds: &[MatchingRound]) -> Result<(), Error> {
+        let transaction = self.connection.unchecked_transaction()?;
+
+        transaction.execute_batch("DELETE FROM matches; DELETE FROM rounds;")?;
+
+        for round in rounds {
+            insert_matching_round(&transaction, round)?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+fn insert_matching_round(transaction: &Transaction, round: &MatchingRound) -> rusqlite::Result<()> {
+    transaction.execute(
+        "INSERT INTO rounds
+                 (id, date, seed, strategy, number_of_tries, intervall_weeks, feedback_dates)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            round.id,
+            to_text(&round.date),
+            round.seed.map(|s| s as i64),
+            round.strategy.as_ref().map(to_text),
+            round.number_of_tries,
+            round.intervall_weeks,
+            to_optional_json(&Some(&round.feedback_dates).filter(|d| !d.is_empty())),
+        ],
+    )?;
+
+    let round_position = transaction.last_insert_rowid();
+
+    for (position, group_match) in round.matches.iter().enumerate() {
+        let giver = &group_match.giver;
+        let receiver = &group_match.receiver;
+
+        transaction.execute(
+            "INSERT INTO matches (round_position, position,
+                    giver_id, giver_group_id, giver_first_name, giver_last_name, giver_gender,
+                    giver_pronouns, giver_locale,
+                    receiver_id, receiver_group_id, receiver_first_name, receiver_last_name,
+                    receiver_gender, receiver_pronouns, receiver_locale, score)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                    ?17)",
+            params![
+                round_position,
+                position,
+                giver.id,
+                giver.group_id,
+                giver.first_name,
+                giver.last_name,
+                to_text(&giver.gender),
+                to_optional_json(&giver.pronouns),
+                giver.locale.as_ref().map(to_text),
+                receiver.id,
+                receiver.group_id,
+                receiver.first_name,
+                receiver.last_name,
+                to_text(&receiver.gender),
+                to_optional_json(&receiver.pronouns),
+                receiver.locale.as_ref().map(to_text),
+                group_match.score,
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Reads the participant from the seven columns starting at the first column
+fn read_match_participant(row: &Row, first_column: usize) -> rusqlite::Result<MatchParticipant> {
+    Ok(MatchParticipant {
+        id: row.get(first_column)?,
+        group_id: row.get(first_column + 1)?,
+        first_name: row.get(first_column + 2)?,
+        last_name: row.get(first_column + 3)?,
+        gender: from_text(row, first_column + 4)?,
+        pronouns: from_optional_json(row, first_column + 5)?,
+        locale: from_optional_text(row, first_column + 6)?,
+    })
+}
+
+/// Stores enums and dates as the same strings as in the JSON files
+fn to_text<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value).expect("Failed to serialize the value") {
+        Value::String(text) => text,
+        value => value.to_string(),
+    }
+}
+
+fn from_text<T: DeserializeOwned>(row: &Row, column: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(column)?;
+
+    serde_json::from_value(Value::String(text))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(column, Type::Text, Box::new(e)))
+}
+
+fn from_optional_text<T: DeserializeOwned>(
+    row: &Row,
+    column: usize,
+) -> rusqlite::Result<Option<T>> {
+    match row.get::<_, Option<String>>(column)? {
+        Some(_) => from_text(row, column).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Stores structured values as JSON
+fn to_optional_json<T: Serialize>(value: &Option<T>) -> Option<String> {
+    value
+        .as_ref()
+        .map(|v| serde_json::to_string(v).expect("Failed to serialize the value"))
+}
+
+fn from_optional_json<T: DeserializeOwned>(
+    row: &Row,
+    column: usize,
+) -> rusqlite::Result<Option<T>> {
+    let Some(json) = row.get::<_, Option<String>>(column)? else {
+        return Ok(None);
+    };
+
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(column, Type::Text, Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::matcher::MatchingStrategy;
+    use crate::structs::matching_round::GroupFeedbackDate;
+    use crate::structs::participant::{Gender, Pronouns};
+    use crate::test_fixtures::{self, match_participant, participant};
+    use time::macros::date;
+
+    fn matching_round(id: i32, seed: Option<u64>) -> MatchingRound {
+        let giver = MatchParticipant {
+            first_name: "Anna".to_string(),
+            last_name: "Alt".to_string(),
+            ..match_participant(1, 1)
+        };
+        let receiver = MatchParticipant {
+            first_name: "Ben".to_string(),
+            last_name: "Bauer".to_string(),
+            gender: Gender::Other,
+            pronouns: Some(Pronouns {
+                accusative: "xier".to_string(),
+                dative: "xiem".to_string(),
+                locale: Locale::De,
+            }),
+            locale: Some(Locale::En),
+            ..match_participant(2, 2)
+        };
+        let matches = vec![Match {
+            giver,
+            receiver,
+            score: 42,
+        }];
+
+        MatchingRound {
+            seed,
+            strategy: seed.map(|_| MatchingStrategy::Greedy),
+            number_of_tries: seed.map(|_| 50),
+            intervall_weeks: seed.map(|_| 4),
+            feedback_dates: seed
+                .map(|_| GroupFeedbackDate {
+                    group_id: 1,
+                    date: date!(2024 - 04 - 04),
+                })
+                .into_iter()
+                .collect(),
+            ..test_fixtures::matching_round(id, date!(2024 - 03 - 07), matches)
+        }
+    }
+
+    fn storage() -> SqliteStorage {
+        SqliteStorage::from_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_participants_round_trip() {
+        let storage = storage();
+        let participants_file = ParticipantsFile {
+            groups: vec![
+                ParticipantsGroup {
+                    id: 7,
+                    participants: vec![
+                        participant(3),
+                        Participant {
+                            gender: Gender::Other,
+                            pronouns: Some(Pronouns {
+                                accusative: "xier".to_string(),
+                                dative: "xiem".to_string(),
+                                locale: Locale::De,
+                            }),
+                            locale: Some(Locale::En),
+                            email: Some("first1@example.com".to_string()),
+                            webhook_url: Some("https://chat.example.com/hooks/1".to_string()),
+                            ..participant(1)
+                        },
+                    ],
+                    excluded_participants: vec![participant(2)],
+                    locale: Some(Locale::En),
+                    feedback_weekday: Some(Weekday::Tuesday),
+                },
+                ParticipantsGroup {
+                    id: 2,
+                    participants: vec![participant(4)],
+                    excluded_participants: vec![],
+                    locale: None,
+                    feedback_weekday: None,
+                },
+            ],
+            constraints: Constraints {
+                forbidden_pairs: vec![PairConstraint {
+                    giver_id: 3,
+                    receiver_id: 1,
+                }],
+                required_pairs: vec![],
+            },
+        };
+
+        storage.save_participants(&participants_file).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(storage.read_participants().unwrap()).unwrap(),
+            serde_json::to_value(&participants_file).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_matching_rounds_round_trip() {
+        let storage = storage();
+        let rounds = vec![
+            matching_round(1, None),
+            matching_round(2, Some(u64::MAX)),
+            matching_round(3, Some(7)),
+        ];
+
+        storage.update_all_existing_rounds(&rounds[..2]).unwrap();
+        storage.save_matching_round(rounds[2].clone()).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(storage.read_matching_rounds().unwrap()).unwrap(),
+            serde_json::to_value(&rounds).unwrap()
+        );
+
+        storage.delete_last_matching_round().unwrap();
+
+        assert_eq!(
+            serde_json::to_value(storage.read_matching_rounds().unwrap()).unwrap(),
+            serde_json::to_value(&rounds[..2]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use time::{Date, Duration, Weekday};
+
+/// A public holiday, an offsite or any other day or range of days without feedback meetings
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BlockedDays {
+    pub date: Date,
+    /// The last blocked day of a range, only the date itself is blocked if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<Date>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl BlockedDays {
+    pub fn contains(&self, date: Date) -> bool {
+        self.date <= date && date <= self.until.unwrap_or(self.date)
+    }
+}
+
+/// The days on which no feedback date is suggested, read from `holidays.json`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HolidayCalendar {
+    #[serde(default)]
+    pub blocked_days: Vec<BlockedDays>,
+}
+
+impl HolidayCalendar {
+    pub fn is_blocked(&self, date: Date) -> bool {
+        self.blocked_days.iter().any(|days| days.contains(date))
+    }
+
+    /// The date itself if it isn't blocked, otherwise the next working day that isn't blocked
+    pub fn next_free_day(&self, date: Date) -> Date {
+        let mut free_day = date;
+
+        while self.is_blocked(free_day) {
+            free_day += Duration::days(1);
+
+            while matches!(free_day.weekday(), Weekday::Saturday | Weekday::Sunday) {
+                free_day += Duration::days(1);
+            }
+        }
+
+        free_day
+    }
+}
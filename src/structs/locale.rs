@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use time::macros::format_description;
+use time::Date;
+
+use super::participant::{Gender, Pronouns};
+
+/// The language of the messages for a participant
+#[derive(
+    clap::ValueEnum, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Default,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    De,
+    En,
+}
+
+impl Locale {
+    /// The code used in file names and the JSON files, e.g. "en"
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::De => "de",
+            Locale::En => "en",
+        }
+    }
+
+    /// The subject of messages sent as email
+    pub fn message_subject(&self) -> &'static str {
+        match self {
+            Locale::De => "Dein Feedbackempfänger",
+            Locale::En => "Your feedback receiver",
+        }
+    }
+
+    /// The title of the calendar event of the feedback conversation
+    pub fn feedback_event_title(&self, giver_name: &str, receiver_name: &str) -> String {
+        match self {
+            Locale::De => format!("Feedback von {giver_name} für {receiver_name}"),
+            Locale::En => format!("Feedback from {giver_name} to {receiver_name}"),
+        }
+    }
+
+    /// The number as a word, intervals of more than eight weeks are called eight weeks
+    pub fn number_word(&self, number: i32) -> &'static str {
+        let words = match self {
+            Locale::De => ["zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht"],
+            Locale::En => ["two", "three", "four", "five", "six", "seven", "eight"],
+        };
+
+        words[(number.clamp(2, 8) - 2) as usize]
+    }
+
+    /// The day and month of the date, e.g. "14.03." or "March 14"
+    pub fn format_day(&self, date: Date) -> String {
+        let result = match self {
+            Locale::De => date.format(format_description!("[day].[month].")),
+            Locale::En => date.format(format_description!("[month repr:long] [day padding:none]")),
+        };
+
+        result.expect("Failed to format the date")
+    }
+
+    /// The pronouns of the gender, there are none for the gender "Other"
+    pub fn gender_pronouns(&self, gender: &Gender) -> Option<Pronouns> {
+        let (accusative, dative) = match (self, gender) {
+            (_, Gender::Other) => return None,
+            (Locale::De, Gender::Male) => ("ihn", "ihm"),
+            (Locale::De, Gender::Female) => ("sie", "ihr"),
+            // English has a single object case
+            (Locale::En, Gender::Male) => ("him", "him"),
+            (Locale::En, Gender::Female) => ("her", "her"),
+        };
+
+        Some(Pronouns {
+            accusative: accusative.to_string(),
+            dative: dative.to_string(),
+            locale: *self,
+        })
+    }
+}
@@ -1,3 +1,4 @@
+use crate::matching::matcher::MatchingStrategy;
 use crate::structs::r#match::Match;
 use serde::{Deserialize, Serialize};
 use time::Date;
@@ -7,4 +8,33 @@ pub struct MatchingRound {
     pub id: i32,
     pub date: Date,
     pub matches: Vec<Match>,
+    /// The seed of the random number generator the matches were created with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<MatchingStrategy>,
+    /// The number of tries of the greedy strategy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number_of_tries: Option<i32>,
+    /// The number of weeks until the feedback of the round is due
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intervall_weeks: Option<i32>,
+    /// The feedback date that was suggested to every group
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feedback_dates: Vec<GroupFeedbackDate>,
+}
+
+impl MatchingRound {
+    pub fn get_feedback_date(&self, group_id: i32) -> Option<Date> {
+        self.feedback_dates
+            .iter()
+            .find(|d| d.group_id == group_id)
+            .map(|d| d.date)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GroupFeedbackDate {
+    pub group_id: i32,
+    pub date: Date,
 }
@@ -1,3 +1,5 @@
+pub mod calendar;
+pub mod locale;
 pub mod r#match;
 pub mod matching_round;
 pub mod participant;
@@ -1,9 +1,22 @@
+use super::locale::Locale;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(clap::ValueEnum, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Gender {
     Male,
     Female,
+    Other,
+}
+
+/// A custom pronoun set in the cases the messages need, e.g. "ihn" and "ihm". English only
+/// has one object case, so both are the same there.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Pronouns {
+    pub accusative: String,
+    pub dative: String,
+    /// The language of the pronouns, they are only used for messages in it
+    #[serde(default)]
+    pub locale: Locale,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -12,6 +25,17 @@ pub struct Participant {
     pub first_name: String,
     pub last_name: String,
     pub gender: Gender,
+    /// Overrides the pronouns derived from the gender
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pronouns: Option<Pronouns>,
+    /// Overrides the locale of the group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Locale>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// The incoming webhook of the participant's direct messages, the messages are delivered to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -21,6 +45,11 @@ pub struct MatchParticipant {
     pub first_name: String,
     pub last_name: String,
     pub gender: Gender,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pronouns: Option<Pronouns>,
+    /// The locale of the participant or their group at the time of the match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Locale>,
 }
 
 // TODO do this with traits, share the function for both structs
@@ -34,21 +63,36 @@ impl MatchParticipant {
     pub fn full_name(&self) -> String {
         format!("{} {}", self.first_name, self.last_name)
     }
+
+    pub fn get_locale(&self) -> Locale {
+        self.locale.unwrap_or_default()
+    }
+
+    /// The custom pronouns in the locale, or the ones of the gender. There are none for the
+    /// gender "Other", messages use the name of the participant instead.
+    pub fn get_pronouns(&self, locale: Locale) -> Option<Pronouns> {
+        match &self.pronouns {
+            Some(pronouns) if pronouns.locale == locale => Some(pronouns.clone()),
+            _ => locale.gender_pronouns(&self.gender),
+        }
+    }
 }
 
 pub fn map_participants_to_match_participants(
-    participants: &Vec<Participant>,
+    participants: &[Participant],
     group_id: i32,
+    group_locale: Option<Locale>,
 ) -> Vec<MatchParticipant> {
     participants
         .iter()
-        .map(|p| map_participant_to_match_participant(p, group_id))
+        .map(|p| map_participant_to_match_participant(p, group_id, group_locale))
         .collect()
 }
 
 pub fn map_participant_to_match_participant(
     participant: &Participant,
     group_id: i32,
+    group_locale: Option<Locale>,
 ) -> MatchParticipant {
     MatchParticipant {
         id: participant.id,
@@ -56,5 +100,7 @@ pub fn map_participant_to_match_participant(
         first_name: participant.first_name.clone(),
         last_name: participant.last_name.clone(),
         gender: participant.gender.clone(),
+        pronouns: participant.pronouns.clone(),
+        locale: participant.locale.or(group_locale),
     }
 }
@@ -1,14 +1,229 @@
+use crate::error::Error;
+use crate::structs::locale::Locale;
 use crate::structs::participant::Participant;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use time::Weekday;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ParticipantsGroup {
     pub id: i32,
     pub participants: Vec<Participant>,
     pub excluded_participants: Vec<Participant>,
+    /// The locale of the participants without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Locale>,
+    /// The weekday of the feedback meetings, Thursday if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback_weekday: Option<Weekday>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PairConstraint {
+    pub giver_id: u32,
+    pub receiver_id: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Constraints {
+    /// Pairs that must never be matched
+    #[serde(default)]
+    pub forbidden_pairs: Vec<PairConstraint>,
+    /// Pairs that must be matched in the next round
+    #[serde(default)]
+    pub required_pairs: Vec<PairConstraint>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ParticipantsFile {
     pub groups: Vec<ParticipantsGroup>,
+    #[serde(default)]
+    pub constraints: Constraints,
+}
+
+impl Constraints {
+    pub fn is_forbidden(&self, giver_id: u32, receiver_id: u32) -> bool {
+        self.forbidden_pairs.contains(&PairConstraint {
+            giver_id,
+            receiver_id,
+        })
+    }
+}
+
+impl ParticipantsGroup {
+    pub fn new(id: i32) -> ParticipantsGroup {
+        ParticipantsGroup {
+            id,
+            participants: vec![],
+            excluded_participants: vec![],
+            locale: None,
+            feedback_weekday: None,
+        }
+    }
+}
+
+impl ParticipantsFile {
+    /// An id that no participant of the file or of the given past matches has, ids of removed
+    /// participants aren't reused so they keep their history
+    pub fn next_participant_id(&self, used_ids: impl IntoIterator<Item = u32>) -> u32 {
+        self.all_participants()
+            .map(|p| p.id)
+            .chain(used_ids)
+            .max()
+            .map_or(1, |id| id + 1)
+    }
+
+    pub fn all_participants(&self) -> impl Iterator<Item = &Participant> {
+        self.groups
+            .iter()
+            .flat_map(|g| g.participants.iter().chain(g.excluded_participants.iter()))
+    }
+
+    /// The current email addresses by participant id, the matches don't store them
+    pub fn get_emails(&self) -> HashMap<u32, String> {
+        self.all_participants()
+            .filter_map(|p| p.email.clone().map(|email| (p.id, email)))
+            .collect()
+    }
+
+    /// Adds the participant to the group, which is created if it doesn't exist yet
+    pub fn add_participant(&mut self, group_id: i32, participant: Participant, excluded: bool) {
+        if !self.groups.iter().any(|g| g.id == group_id) {
+            self.groups.push(ParticipantsGroup::new(group_id));
+        }
+
+        let group = self
+            .groups
+            .iter_mut()
+            .find(|g| g.id == group_id)
+            .expect("The group has just been created");
+
+        match excluded {
+            true => group.excluded_participants.push(participant),
+            false => group.participants.push(participant),
+        }
+    }
+
+    /// Removes the participant and the pair constraints with them. Returns the removed
+    /// participant and whether they were excluded.
+    pub fn remove_participant(
+        &mut self,
+        participant_id: u32,
+    ) -> Result<(Participant, bool), Error> {
+        let removed = self.groups.iter_mut().find_map(|group| {
+            take_participant(&mut group.participants, participant_id)
+                .map(|p| (p, false))
+                .or_else(|| {
+                    take_participant(&mut group.excluded_participants, participant_id)
+                        .map(|p| (p, true))
+                })
+        });
+
+        let removed = removed.ok_or_else(|| participant_not_found(participant_id, ""))?;
+
+        let constraints = &mut self.constraints;
+        for pairs in [
+            &mut constraints.forbidden_pairs,
+            &mut constraints.required_pairs,
+        ] {
+            pairs.retain(|p| p.giver_id != participant_id && p.receiver_id != participant_id);
+        }
+
+        Ok(removed)
+    }
+
+    /// Moves the participant from the participants to the excluded participants of their group,
+    /// or back if `excluded` is false
+    pub fn set_excluded(&mut self, participant_id: u32, excluded: bool) -> Result<(), Error> {
+        for group in &mut self.groups {
+            let (from, to) = match excluded {
+                true => (&mut group.participants, &mut group.excluded_participants),
+                false => (&mut group.excluded_participants, &mut group.participants),
+            };
+
+            if let Some(participant) = take_participant(from, participant_id) {
+                to.push(participant);
+                return Ok(());
+            }
+        }
+
+        Err(participant_not_found(
+            participant_id,
+            match excluded {
+                true => "active ",
+                false => "excluded ",
+            },
+        ))
+    }
+
+    /// Moves the participant into the group, keeping them excluded if they were
+    pub fn move_participant(&mut self, participant_id: u32, group_id: i32) -> Result<(), Error> {
+        let constraints = self.constraints.clone();
+        let (participant, excluded) = self.remove_participant(participant_id)?;
+
+        self.add_participant(group_id, participant, excluded);
+        self.constraints = constraints;
+
+        Ok(())
+    }
+}
+
+fn take_participant(
+    participants: &mut Vec<Participant>,
+    participant_id: u32,
+) -> Option<Participant> {
+    participants
+        .iter()
+        .position(|p| p.id == participant_id)
+        .map(|index| participants.remove(index))
+}
+
+fn participant_not_found(participant_id: u32, kind: &str) -> Error {
+    Error::NotFound(format!(
+        "No {kind}participant with id {participant_id} has been found"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::participant;
+
+    #[test]
+    fn test_participants_are_managed_consistently() {
+        let mut participants_file = ParticipantsFile {
+            groups: vec![ParticipantsGroup {
+                participants: vec![participant(1), participant(2)],
+                ..ParticipantsGroup::new(1)
+            }],
+            constraints: Constraints {
+                forbidden_pairs: vec![PairConstraint {
+                    giver_id: 1,
+                    receiver_id: 2,
+                }],
+                required_pairs: vec![],
+            },
+        };
+
+        // Ids of the history aren't reused
+        assert_eq!(participants_file.next_participant_id([7]), 8);
+        assert_eq!(participants_file.next_participant_id([]), 3);
+
+        participants_file.set_excluded(2, true).unwrap();
+        assert!(participants_file.set_excluded(2, true).is_err());
+        participants_file.move_participant(2, 3).unwrap();
+
+        let new_group = &participants_file.groups[1];
+        assert_eq!(new_group.id, 3);
+        assert_eq!(new_group.excluded_participants[0].id, 2);
+        assert_eq!(participants_file.constraints.forbidden_pairs.len(), 1);
+
+        participants_file.remove_participant(1).unwrap();
+        assert!(participants_file.groups[0].participants.is_empty());
+        assert!(participants_file.constraints.forbidden_pairs.is_empty());
+        assert!(matches!(
+            participants_file.remove_participant(1),
+            Err(Error::NotFound(_))
+        ));
+    }
 }
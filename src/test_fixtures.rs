@@ -0,0 +1,55 @@
+use crate::structs::matching_round::MatchingRound;
+use crate::structs::participant::{Gender, MatchParticipant, Participant};
+use crate::structs::r#match::Match;
+
+use time::Date;
+
+/// A participant named after the id, tests override the fields they need
+pub fn participant(id: u32) -> Participant {
+    Participant {
+        id,
+        first_name: format!("First{id}"),
+        last_name: format!("Last{id}"),
+        gender: Gender::Female,
+        pronouns: None,
+        locale: None,
+        email: None,
+        webhook_url: None,
+    }
+}
+
+/// A match participant named after the id, tests override the fields they need
+pub fn match_participant(id: u32, group_id: i32) -> MatchParticipant {
+    MatchParticipant {
+        id,
+        group_id,
+        first_name: format!("First{id}"),
+        last_name: format!("Last{id}"),
+        gender: Gender::Female,
+        pronouns: None,
+        locale: None,
+    }
+}
+
+/// A match between two participants of group 1
+pub fn pair_match(giver_id: u32, receiver_id: u32, score: i64) -> Match {
+    Match {
+        giver: match_participant(giver_id, 1),
+        receiver: match_participant(receiver_id, 1),
+        score,
+    }
+}
+
+/// A round without the settings, interval and feedback dates
+pub fn matching_round(id: i32, date: Date, matches: Vec<Match>) -> MatchingRound {
+    MatchingRound {
+        id,
+        date,
+        matches,
+        seed: None,
+        strategy: None,
+        number_of_tries: None,
+        intervall_weeks: None,
+        feedback_dates: vec![],
+    }
+}